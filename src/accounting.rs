@@ -1,11 +1,14 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
-/// One JSONL record per accepted submission.
+use crate::api::types::{Challenge, CryptoReceipt, RegistrationReceiptInner};
+use crate::store::{ExportCounts, Store};
+
+/// STAR per NIGHT.
+pub const STAR_PER_NIGHT: f64 = 1_000_000.0;
+
+/// One accepted submission.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReceiptRecord {
     /// ISO string from the API receipt (e.g. "2025-11-04T21:26:06.133Z")
@@ -18,15 +21,33 @@ pub struct ReceiptRecord {
     pub day: u32,
     /// Challenge number within the day (e.g. 22)
     pub challenge_number: u32,
+    /// Nonce we submitted (hex); missing in receipts stored before it was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Server-signed receipt proving the solution was accepted; missing if it
+    /// was accepted without one (or stored before it was kept)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crypto_receipt: Option<CryptoReceipt>,
 }
 
-/// Holds file locations and utilities for accounting.
-/// All files are stored inside the keystore directory:
-///   - 00receipts.jsonl: one receipt per line
-///   - 00star_rates.json: JSON array of per-day STAR rates (index 0 = day 1)
+/// A registered address and the server's registration receipt for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationRecord {
+    pub address: String,
+    /// Payment public key we registered (hex)
+    pub pubkey: String,
+    /// When we first registered the address
+    pub registered_at: String,
+    /// Receipt of the latest registration; missing for addresses registered
+    /// before it was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<RegistrationReceiptInner>,
+}
+
+/// Receipts and STAR rates, kept in the SQLite store in the keystore
+/// directory (see `Store`).
 pub struct Accounting {
-    receipts_path: PathBuf,
-    star_rates_path: PathBuf,
+    store: Store,
 }
 
 impl Accounting {
@@ -38,74 +59,90 @@ impl Accounting {
 
     /// Construct using an explicit keystore directory.
     pub fn new<P: AsRef<Path>>(keystore_dir: P) -> Result<Self> {
-        let root = keystore_dir.as_ref();
-        fs::create_dir_all(root)?;
-        let receipts_path = root.join("00receipts.jsonl");
-        let star_rates_path = root.join("00star_rates.json");
-        Ok(Self { receipts_path, star_rates_path })
+        Ok(Self { store: Store::new(keystore_dir)? })
     }
 
-    /// Append one receipt (as a JSON object on its own line).
+    /// Store one receipt (a second one for the same address and challenge
+    /// is ignored).
     pub fn append_receipt(&self, rec: &ReceiptRecord) -> Result<()> {
-        let mut f = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.receipts_path)?;
-        let line = serde_json::to_string(rec)?;
-        writeln!(f, "{}", line)?;
+        self.store.insert_receipt(rec)?;
         Ok(())
     }
 
-    /// Read all receipts (fast enough for typical sizes).
+    /// Read all receipts, oldest first.
     pub fn read_all_receipts(&self) -> Result<Vec<ReceiptRecord>> {
-        if !self.receipts_path.exists() {
-            return Ok(Vec::new());
-        }
-        let f = OpenOptions::new().read(true).open(&self.receipts_path)?;
-        let reader = BufReader::new(f);
-        let mut out = Vec::new();
-        for line in reader.lines() {
-            let line = line?;
-            if line.trim().is_empty() { continue; }
-            match serde_json::from_str::<ReceiptRecord>(&line) {
-                Ok(rec) => out.push(rec),
-                Err(e) => {
-                    // keep going if there's a corrupt line
-                    tracing::warn!("Ignoring malformed receipt line: {e}");
-                }
-            }
-        }
-        Ok(out)
+        self.store.receipts()
+    }
+
+    /// Receipts from UTC days `since..=until` (either bound optional).
+    pub fn receipts_between(
+        &self,
+        since: Option<chrono::NaiveDate>,
+        until: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<ReceiptRecord>> {
+        let since = since.map(|d| d.to_string());
+        let until = until.and_then(|d| d.succ_opt()).map(|d| d.to_string());
+        self.store.receipts_between(since.as_deref(), until.as_deref())
+    }
+
+    /// Whether `address` has at least one receipt.
+    pub fn has_receipts(&self, address: &str) -> Result<bool> {
+        self.store.has_receipts(address)
+    }
+
+    /// Every challenge we have seen.
+    pub fn challenges(&self) -> Result<Vec<Challenge>> {
+        self.store.challenges()
     }
 
     /// Persist daily STAR rates (index 0 => day 1).
     pub fn write_star_rates(&self, rates: &[u64]) -> Result<()> {
-        let tmp = serde_json::to_string_pretty(rates)?;
-        fs::write(&self.star_rates_path, tmp)?;
-        Ok(())
+        self.store.set_star_rates(rates)
     }
 
     /// Load daily STAR rates (index 0 => day 1). Empty if not present.
     pub fn read_star_rates(&self) -> Result<Vec<u64>> {
-        if !self.star_rates_path.exists() {
-            return Ok(Vec::new());
-        }
-        let txt = fs::read_to_string(&self.star_rates_path)?;
-        let v: Vec<u64> = serde_json::from_str(&txt)?;
-        Ok(v)
+        self.store.star_rates()
+    }
+
+    /// Remember a successful `/register` of `address` and its receipt.
+    pub fn record_registration(
+        &self,
+        address: &str,
+        pubkey_hex: &str,
+        receipt: &RegistrationReceiptInner,
+    ) -> Result<()> {
+        self.store.record_registration(address, pubkey_hex, receipt)
+    }
+
+    /// Registration of `address`, if we registered it.
+    pub fn registration(&self, address: &str) -> Result<Option<RegistrationRecord>> {
+        self.store.registration(address)
+    }
+
+    /// Log one submission attempt of `nonce` and its outcome.
+    pub fn record_attempt(
+        &self,
+        address: &str,
+        challenge_id: &str,
+        nonce: &str,
+        outcome: &str,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        self.store.record_attempt(address, challenge_id, nonce, outcome, detail)
+    }
+
+    /// Write receipts, registrations, donations, challenges and STAR rates as
+    /// JSONL to `dir`.
+    pub fn export_jsonl(&self, dir: &Path) -> Result<ExportCounts> {
+        self.store.export_jsonl(dir)
     }
 
     /// Compute totals: (solutions_count, total_star, total_night_float)
     pub fn totals(&self) -> Result<(u64, u128, f64)> {
-        let receipts = self.read_all_receipts()?;
+        let by_day = self.store.receipts_per_day()?;
         let star_rates = self.read_star_rates()?;
 
-        // Group receipts by day: day => count
-        let mut by_day: HashMap<u32, u64> = HashMap::new();
-        for r in receipts.iter() {
-            *by_day.entry(r.day).or_insert(0) += 1;
-        }
-
         // Sum STAR = sum_over_day(count(day) * rate(day))
         let mut total_star: u128 = 0;
         for (day, count) in by_day {
@@ -120,8 +157,8 @@ impl Accounting {
             }
         }
 
-        let solutions = receipts.len() as u64;
-        let total_night = (total_star as f64) / 1_000_000.0;
+        let solutions = self.store.receipt_count()?;
+        let total_night = total_star as f64 / STAR_PER_NIGHT;
         Ok((solutions, total_star, total_night))
     }
 
@@ -0,0 +1,280 @@
+use anyhow::{bail, Context, Result};
+use bech32::FromBase32;
+use ciborium::value::Value;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use super::hd::XPrv;
+use super::key::PrivateKey;
+use crate::util::bech::bech32_encode;
+
+/// Which key of an address a file holds (selects the cardano-cli type names).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRole {
+    Payment,
+    Stake,
+}
+
+impl KeyRole {
+    fn prefix(&self) -> &'static str {
+        match self {
+            KeyRole::Payment => "Payment",
+            KeyRole::Stake => "Stake",
+        }
+    }
+}
+
+/// A signing key as exported: extended keys also carry their BIP32 chain
+/// code (unknown for imported `ed25519e_sk` keys).
+#[derive(Clone)]
+pub struct CardanoKey {
+    pub key: PrivateKey,
+    pub chain_code: Option<[u8; 32]>,
+}
+
+/// cardano-cli key file (`.skey` / `.vkey`).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TextEnvelope {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub description: String,
+    #[serde(rename = "cborHex")]
+    pub cbor_hex: String,
+}
+
+impl TextEnvelope {
+    fn new(kind: String, description: &str, bytes: &[u8]) -> Self {
+        let mut cbor = Vec::new();
+        ciborium::ser::into_writer(&Value::Bytes(bytes.to_vec()), &mut cbor).expect("CBOR to Vec");
+        Self {
+            kind,
+            description: description.to_string(),
+            cbor_hex: hex::encode(cbor),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&data).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Written like cardano-cli does, readable by the owner only.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut data = serde_json::to_string_pretty(self)?;
+        data.push('\n');
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create_new(true);
+        // Set at creation, so a signing key is never readable by others
+        #[cfg(unix)]
+        if self.kind.contains("SigningKey") {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        opts.open(path)
+            .and_then(|mut f| f.write_all(data.as_bytes()))
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// The raw key bytes inside `cborHex`.
+    pub fn key_bytes(&self) -> Result<Vec<u8>> {
+        let cbor = hex::decode(&self.cbor_hex).context("cborHex is not hex")?;
+        match ciborium::de::from_reader(cbor.as_slice())? {
+            Value::Bytes(b) => Ok(b),
+            _ => bail!("cborHex must hold a byte string"),
+        }
+    }
+}
+
+/// `.skey` envelope: `PaymentSigningKeyShelley_ed25519` for seeds,
+/// `PaymentExtendedSigningKeyShelley_ed25519_bip32` (kL || kR || A || cc)
+/// for extended keys.
+pub fn signing_envelope(role: KeyRole, k: &CardanoKey) -> TextEnvelope {
+    match &k.key {
+        PrivateKey::Seed(sk) => TextEnvelope::new(
+            format!("{}SigningKeyShelley_ed25519", role.prefix()),
+            &format!("{} Signing Key", role.prefix()),
+            sk,
+        ),
+        PrivateKey::Extended(xsk) => {
+            let mut bytes = xsk.to_vec();
+            bytes.extend_from_slice(&k.key.public_key());
+            bytes.extend_from_slice(&k.chain_code.unwrap_or([0u8; 32]));
+            TextEnvelope::new(
+                format!("{}ExtendedSigningKeyShelley_ed25519_bip32", role.prefix()),
+                &format!("{} Signing Key", role.prefix()),
+                &bytes,
+            )
+        }
+    }
+}
+
+/// `.vkey` envelope matching [`signing_envelope`].
+pub fn verification_envelope(role: KeyRole, k: &CardanoKey) -> TextEnvelope {
+    let pk = k.key.public_key();
+    match &k.key {
+        PrivateKey::Seed(_) => TextEnvelope::new(
+            format!("{}VerificationKeyShelley_ed25519", role.prefix()),
+            &format!("{} Verification Key", role.prefix()),
+            &pk,
+        ),
+        PrivateKey::Extended(_) => {
+            let mut bytes = pk.to_vec();
+            bytes.extend_from_slice(&k.chain_code.unwrap_or([0u8; 32]));
+            TextEnvelope::new(
+                format!("{}ExtendedVerificationKeyShelley_ed25519_bip32", role.prefix()),
+                &format!("{} Verification Key", role.prefix()),
+                &bytes,
+            )
+        }
+    }
+}
+
+/// Bech32 signing key: `ed25519_sk1…` for seeds, `ed25519e_sk1…` for
+/// extended keys (CIP-5).
+pub fn signing_bech32(key: &PrivateKey) -> String {
+    match key {
+        PrivateKey::Seed(sk) => bech32_encode("ed25519_sk", sk),
+        PrivateKey::Extended(xsk) => bech32_encode("ed25519e_sk", xsk),
+    }
+}
+
+/// Signing key from a cardano-cli `.skey` file or a bech32 string
+/// (`ed25519_sk`, `ed25519e_sk`, `addr_sk`, `addr_xsk`, `stake_sk`, `stake_xsk`).
+pub fn parse_signing_key(input: &str) -> Result<PrivateKey> {
+    let input = input.trim();
+    let path = Path::new(input);
+    if path.is_file() {
+        return from_envelope(&TextEnvelope::read(path)?)
+            .with_context(|| format!("reading key from {}", path.display()));
+    }
+
+    let Ok((hrp, data, _variant)) = bech32::decode(input) else {
+        bail!("{input} is neither a key file nor a bech32 signing key");
+    };
+    let bytes = Vec::<u8>::from_base32(&data)?;
+    match (hrp.as_str(), bytes.len()) {
+        ("ed25519_sk" | "addr_sk" | "stake_sk", 32) => PrivateKey::from_bytes(&bytes),
+        // addr_xsk / stake_xsk carry the chain code after kL || kR
+        ("ed25519e_sk" | "addr_xsk" | "stake_xsk", 64 | 96) => PrivateKey::from_bytes(&bytes[..64]),
+        (hrp, n) => bail!("unsupported signing key {hrp}1… ({n} bytes)"),
+    }
+}
+
+fn from_envelope(env: &TextEnvelope) -> Result<PrivateKey> {
+    let bytes = env.key_bytes()?;
+    if env.kind.ends_with("SigningKeyShelley_ed25519") && bytes.len() == 32 {
+        return PrivateKey::from_bytes(&bytes);
+    }
+    if env.kind.ends_with("ExtendedSigningKeyShelley_ed25519_bip32") && bytes.len() == 128 {
+        let key = PrivateKey::from_bytes(&bytes[..64])?;
+        if key.public_key()[..] != bytes[64..96] {
+            bail!("extended key does not match its embedded public key");
+        }
+        return Ok(key);
+    }
+    bail!("unsupported key type {} ({} bytes)", env.kind, bytes.len())
+}
+
+impl From<&XPrv> for CardanoKey {
+    fn from(x: &XPrv) -> Self {
+        Self {
+            key: x.private_key(),
+            chain_code: Some(x.chain_code()),
+        }
+    }
+}
+
+/// Keys of one keystore address, as handed to `export`.
+pub struct ExportedAddress {
+    pub address: String,
+    pub payment: CardanoKey,
+    pub stake: Option<CardanoKey>,
+}
+
+impl ExportedAddress {
+    /// cardano-cli layout: `payment.skey`, `payment.vkey`, `payment.addr`
+    /// and, for per-address stake keys, `stake.skey` / `stake.vkey`.
+    pub fn write_dir(&self, dir: &Path) -> Result<()> {
+        if dir.join("payment.skey").exists() {
+            bail!("{} already holds exported keys", dir.display());
+        }
+        fs::create_dir_all(dir)?;
+
+        let keys = [(KeyRole::Payment, Some(&self.payment)), (KeyRole::Stake, self.stake.as_ref())];
+        for (role, key) in keys {
+            let Some(key) = key else { continue };
+            if matches!(key.key, PrivateKey::Extended(_)) && key.chain_code.is_none() {
+                tracing::warn!("{}: chain code unknown, exported as zeros (signing is unaffected)", self.address);
+            }
+            let name = role.prefix().to_lowercase();
+            signing_envelope(role, key).write(&dir.join(format!("{name}.skey")))?;
+            verification_envelope(role, key).write(&dir.join(format!("{name}.vkey")))?;
+        }
+        fs::write(dir.join("payment.addr"), &self.address)?;
+        Ok(())
+    }
+}
+
+/// Ed25519 public key from hex, a cardano-cli `.vkey` file or a bech32
+/// verification key (`ed25519_pk`, `addr_vk`, `stake_vk`, `addr_xvk`, `stake_xvk`).
+pub fn parse_verification_key(input: &str) -> Result<[u8; 32]> {
+    let input = input.trim();
+    let path = Path::new(input);
+    let bytes = if path.is_file() {
+        TextEnvelope::read(path)?.key_bytes()?
+    } else if let Ok(bytes) = hex::decode(input) {
+        bytes
+    } else {
+        let Ok((hrp, data, _variant)) = bech32::decode(input) else {
+            bail!("{input} is neither a key file, hex nor a bech32 verification key");
+        };
+        if !matches!(hrp.as_str(), "ed25519_pk" | "addr_vk" | "stake_vk" | "addr_xvk" | "stake_xvk") {
+            bail!("unsupported verification key {hrp}1…");
+        }
+        Vec::<u8>::from_base32(&data)?
+    };
+    // Extended keys append the chain code
+    match bytes.len() {
+        32 | 64 => Ok(bytes[..32].try_into().unwrap()),
+        n => bail!("verification key must be 32 bytes (64 with chain code), got {n}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signing_key_file_round_trip() {
+        let dir = std::env::temp_dir().join(format!("scavenger-envelope-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let key = CardanoKey {
+            key: PrivateKey::from_bytes(&[9; 32]).unwrap(),
+            chain_code: None,
+        };
+        ExportedAddress {
+            address: "addr_test1".into(),
+            payment: key.clone(),
+            stake: None,
+        }
+        .write_dir(&dir)
+        .unwrap();
+
+        let skey = dir.join("payment.skey");
+        let vkey = dir.join("payment.vkey");
+        let read = parse_signing_key(skey.to_str().unwrap()).unwrap();
+        assert_eq!(read.public_key(), key.key.public_key());
+        assert_eq!(parse_verification_key(vkey.to_str().unwrap()).unwrap(), key.key.public_key());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&skey).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        // Never overwrites an existing key
+        assert!(signing_envelope(KeyRole::Payment, &key).write(&skey).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
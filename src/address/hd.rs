@@ -0,0 +1,339 @@
+use anyhow::{bail, Context, Result};
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::key::PrivateKey;
+use super::sealed::{write_atomic, KeystoreCipher, Sealed, HD_ROOT_AAD};
+use super::stake::StakeCredential;
+use crate::util::bech::blake2b224;
+
+type HmacSha512 = Hmac<Sha512>;
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// CIP-1852 purpose and Cardano coin type.
+const PURPOSE: u32 = 1852;
+const COIN_TYPE: u32 = 1815;
+/// External chain (payment keys) and staking keys.
+const ROLE_EXTERNAL: u32 = 0;
+const ROLE_STAKING: u32 = 2;
+
+/// BIP32-Ed25519 extended private key: `kL || kR` plus chain code.
+#[derive(Clone)]
+pub struct XPrv {
+    key: [u8; 64],
+    chain_code: [u8; 32],
+}
+
+impl XPrv {
+    /// Icarus master key (CIP-3) from BIP-39 entropy.
+    pub fn from_entropy(entropy: &[u8], passphrase: &str) -> Self {
+        let mut out = [0u8; 96];
+        pbkdf2::pbkdf2_hmac::<Sha512>(passphrase.as_bytes(), entropy, 4096, &mut out);
+        out[0] &= 0b1111_1000;
+        out[31] &= 0b0001_1111;
+        out[31] |= 0b0100_0000;
+        Self::from_bytes(&out)
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> Self {
+        let mut key = [0u8; 64];
+        let mut chain_code = [0u8; 32];
+        key.copy_from_slice(&bytes[..64]);
+        chain_code.copy_from_slice(&bytes[64..]);
+        Self { key, chain_code }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut out = [0u8; 96];
+        out[..64].copy_from_slice(&self.key);
+        out[64..].copy_from_slice(&self.chain_code);
+        out
+    }
+
+    pub fn private_key(&self) -> PrivateKey {
+        PrivateKey::Extended(self.key)
+    }
+
+    pub fn chain_code(&self) -> [u8; 32] {
+        self.chain_code
+    }
+
+    /// Child key `index` (BIP32-Ed25519, V2 scheme as used by Cardano).
+    pub fn derive(&self, index: u32) -> Self {
+        let mut zmac = HmacSha512::new_from_slice(&self.chain_code).expect("HMAC takes any key length");
+        let mut cmac = zmac.clone();
+
+        if index >= HARDENED {
+            zmac.update(&[0x00]);
+            zmac.update(&self.key);
+            cmac.update(&[0x01]);
+            cmac.update(&self.key);
+        } else {
+            let pk = self.private_key().public_key();
+            zmac.update(&[0x02]);
+            zmac.update(&pk);
+            cmac.update(&[0x03]);
+            cmac.update(&pk);
+        }
+        zmac.update(&index.to_le_bytes());
+        cmac.update(&index.to_le_bytes());
+
+        let z = zmac.finalize().into_bytes();
+        let c = cmac.finalize().into_bytes();
+
+        let mut key = [0u8; 64];
+        // kL = 8 * zL[..28] + parent kL
+        let mut carry: u16 = 0;
+        for i in 0..32 {
+            let zl = if i < 28 { (z[i] as u16) << 3 } else { 0 };
+            let r = self.key[i] as u16 + zl + carry;
+            key[i] = r as u8;
+            carry = r >> 8;
+        }
+        // kR = zR + parent kR (mod 2^256)
+        let mut carry: u16 = 0;
+        for i in 0..32 {
+            let r = self.key[32 + i] as u16 + z[32 + i] as u16 + carry;
+            key[32 + i] = r as u8;
+            carry = r >> 8;
+        }
+
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&c[32..]);
+        Self { key, chain_code }
+    }
+}
+
+/// Contents of keystore/00hd.json.
+#[derive(Serialize, Deserialize)]
+struct HdFile {
+    /// Icarus root key `kL || kR || chain_code` (hex), or its encrypted
+    /// form in an encrypted keystore.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root_xprv: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root_xprv_enc: Option<Sealed>,
+    /// First payment index not handed out yet.
+    next_index: u32,
+    /// Stake credential of the derived base addresses (enterprise if unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stake: Option<StakeCredential>,
+}
+
+/// Payment keys derived from one BIP-39 mnemonic along
+/// `m/1852'/1815'/0'/0/i`; only the root key and the next index are stored.
+pub struct HdWallet {
+    path: PathBuf,
+    root: XPrv,
+    account: XPrv,
+    external: XPrv,
+    next_index: Mutex<u32>,
+    stake: Option<StakeCredential>,
+    cipher: Option<Arc<KeystoreCipher>>,
+}
+
+impl HdWallet {
+    pub const FILE: &'static str = "00hd.json";
+
+    /// Fresh 24-word mnemonic.
+    pub fn generate_mnemonic() -> String {
+        use rand::RngCore;
+        let mut entropy = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut entropy);
+        Mnemonic::from_entropy(&entropy)
+            .expect("32 bytes is valid BIP-39 entropy")
+            .to_string()
+    }
+
+    /// Load the HD root from `keystore_dir`, if one was created.
+    pub fn load<P: AsRef<Path>>(
+        keystore_dir: P,
+        cipher: Option<Arc<KeystoreCipher>>,
+    ) -> Result<Option<Self>> {
+        let path = keystore_dir.as_ref().join(Self::FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        let file: HdFile = serde_json::from_str(&data).with_context(|| format!("parsing {}", path.display()))?;
+
+        let root = match (&file.root_xprv, &file.root_xprv_enc, &cipher) {
+            (Some(hex_root), _, _) => hex::decode(hex_root)?,
+            (None, Some(sealed), Some(cipher)) => cipher.unseal(sealed, HD_ROOT_AAD)?,
+            (None, Some(_), None) => bail!("HD root is encrypted but the keystore is not unlocked"),
+            (None, None, _) => bail!("{} holds no root key", path.display()),
+        };
+        let bytes: [u8; 96] = root
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("root_xprv must be 96 bytes"))?;
+
+        let mut wallet = Self::new(path, XPrv::from_bytes(&bytes), file.next_index, cipher);
+        wallet.stake = file.stake;
+        Ok(Some(wallet))
+    }
+
+    /// Create the HD root in `keystore_dir` from `mnemonic`. `next_index`
+    /// is the number of addresses already in use (when restoring); `stake`
+    /// is fixed here so every address re-derives identically.
+    pub fn create<P: AsRef<Path>>(
+        keystore_dir: P,
+        mnemonic: &str,
+        passphrase: &str,
+        next_index: u32,
+        stake: Option<StakeCredential>,
+        cipher: Option<Arc<KeystoreCipher>>,
+    ) -> Result<Self> {
+        let path = keystore_dir.as_ref().join(Self::FILE);
+        if path.exists() {
+            bail!("{} already exists", path.display());
+        }
+        fs::create_dir_all(keystore_dir.as_ref())?;
+
+        let mnemonic = Mnemonic::parse_normalized(mnemonic).context("invalid BIP-39 mnemonic")?;
+        let root = XPrv::from_entropy(&mnemonic.to_entropy(), passphrase);
+
+        let mut wallet = Self::new(path, root, next_index, cipher);
+        wallet.stake = stake;
+        wallet.save(next_index)?;
+        Ok(wallet)
+    }
+
+    fn new(path: PathBuf, root: XPrv, next_index: u32, cipher: Option<Arc<KeystoreCipher>>) -> Self {
+        let account = root
+            .derive(HARDENED | PURPOSE)
+            .derive(HARDENED | COIN_TYPE)
+            .derive(HARDENED);
+        let external = account.derive(ROLE_EXTERNAL);
+        Self {
+            path,
+            root,
+            account,
+            external,
+            next_index: Mutex::new(next_index),
+            stake: None,
+            cipher,
+        }
+    }
+
+    /// Payment key `m/1852'/1815'/0'/0/index`.
+    pub fn derive(&self, index: u32) -> PrivateKey {
+        self.payment_xprv(index).private_key()
+    }
+
+    pub fn payment_xprv(&self, index: u32) -> XPrv {
+        self.external.derive(index)
+    }
+
+    /// Stake key `m/1852'/1815'/0'/2/index` (per-address stake keys only).
+    pub fn stake_xprv(&self, index: u32) -> Option<XPrv> {
+        match &self.stake {
+            Some(StakeCredential::PerAddress) => Some(self.account.derive(ROLE_STAKING).derive(index)),
+            _ => None,
+        }
+    }
+
+    pub fn stake(&self) -> Option<&StakeCredential> {
+        self.stake.as_ref()
+    }
+
+    /// Stake key hash for payment `index` (`None` for enterprise addresses).
+    pub fn stake_key_hash(&self, index: u32) -> Result<Option<[u8; 28]>> {
+        match (&self.stake, self.stake_xprv(index)) {
+            (None, _) => Ok(None),
+            (Some(_), Some(stake)) => Ok(Some(blake2b224(&stake.private_key().public_key()))),
+            (Some(shared), None) => shared.shared_hash(),
+        }
+    }
+
+    pub fn next_index(&self) -> u32 {
+        *self.next_index.lock().unwrap()
+    }
+
+    /// Hand out the next payment index (persisted before returning).
+    pub fn take_index(&self) -> Result<u32> {
+        let mut next = self.next_index.lock().unwrap();
+        let index = *next;
+        if index >= HARDENED {
+            bail!("HD payment index space exhausted");
+        }
+        self.save(index + 1)?;
+        *next = index + 1;
+        Ok(index)
+    }
+
+    fn save(&self, next_index: u32) -> Result<()> {
+        let root = self.root.to_bytes();
+        let file = match &self.cipher {
+            Some(cipher) => HdFile {
+                root_xprv: None,
+                root_xprv_enc: Some(cipher.seal(&root, HD_ROOT_AAD)),
+                next_index,
+                stake: self.stake.clone(),
+            },
+            None => HdFile {
+                root_xprv: Some(hex::encode(root)),
+                root_xprv_enc: None,
+                next_index,
+                stake: self.stake.clone(),
+            },
+        };
+        write_atomic(&self.path, &serde_json::to_vec_pretty(&file)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::stake::address_bytes;
+    use crate::util::bech::bech32_encode;
+
+    fn root(mnemonic: &str, passphrase: &str) -> XPrv {
+        let mnemonic = Mnemonic::parse_normalized(mnemonic).unwrap();
+        XPrv::from_entropy(&mnemonic.to_entropy(), passphrase)
+    }
+
+    // CIP-3, Icarus test vectors.
+    #[test]
+    fn icarus_root_matches_cip3() {
+        let mnemonic = "eight country switch draw meat scout mystery blade tip drift useless good keep usage title";
+        assert_eq!(
+            hex::encode(root(mnemonic, "").to_bytes()),
+            "c065afd2832cd8b087c4d9ab7011f481ee1e0721e78ea5dd609f3ab3f156d245\
+             d176bd8fd4ec60b4731c3918a2a72a0226c0cd119ec35b47e4d55884667f552a\
+             23f7fdcd4a10c6cd2c7393ac61d877873e248f417634aa3d812af327ffe9d620"
+        );
+        assert_eq!(
+            hex::encode(root(mnemonic, "foo").to_bytes()),
+            "70531039904019351e1afb361cd1b312a4d0565d4ff9f8062d38acf4b15cce41\
+             d7b5738d9c893feea55512a3004acb0d222c35d3e3d5cde943a15a9824cbac59\
+             443cf67e589614076ba01e354b1a432e0e6db3b59e37fc56b5fb0222970a010e"
+        );
+    }
+
+    // CIP-19 test vectors: payment key m/1852'/1815'/0'/0/0.
+    #[test]
+    fn payment_key_matches_cip19() {
+        let root = root("test walk nut penalty hip pave soap entry language right filter choice", "");
+        let wallet = HdWallet::new(PathBuf::from(HdWallet::FILE), root, 0, None);
+        let pk = wallet.derive(0).public_key();
+
+        assert_eq!(
+            bech32_encode("addr_vk", &pk),
+            "addr_vk1w0l2sr2zgfm26ztc6nl9xy8ghsk5sh6ldwemlpmp9xylzy4dtf7st80zhd"
+        );
+        assert_eq!(
+            bech32_encode("addr", &address_bytes(1, &pk, None)),
+            "addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8"
+        );
+        assert_eq!(
+            bech32_encode("addr_test", &address_bytes(0, &pk, None)),
+            "addr_test1vz2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzerspjrlsz"
+        );
+    }
+}
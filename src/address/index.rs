@@ -0,0 +1,168 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::sealed::write_atomic;
+
+/// One key file in canonical rotation order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub seq: u64,
+    pub address: String,
+    /// File name inside the keystore.
+    pub file: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexFile {
+    entries: Vec<IndexEntry>,
+}
+
+/// What `repair` changed.
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    pub kept: usize,
+    pub added: usize,
+    pub dropped: usize,
+}
+
+/// Canonical order of the random key files: keystore/00index.json
+///
+/// File mtimes change with rsync or restores, so rotation order comes from
+/// here. HD addresses are ordered by their derivation index instead.
+pub struct KeyIndex {
+    dir: PathBuf,
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl KeyIndex {
+    pub const FILE: &'static str = "00index.json";
+
+    pub fn new<P: AsRef<Path>>(keystore_dir: P) -> Self {
+        let dir = keystore_dir.as_ref().to_path_buf();
+        Self {
+            path: dir.join(Self::FILE),
+            dir,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Entries in canonical order, or `None` if the index is missing.
+    pub fn load(&self) -> Result<Option<Vec<IndexEntry>>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read_to_string(&self.path)?;
+        let mut index: IndexFile =
+            serde_json::from_str(&data).with_context(|| format!("parsing {}", self.path.display()))?;
+        index.entries.sort_by_key(|e| e.seq);
+        Ok(Some(index.entries))
+    }
+
+    /// Record a newly written key file at the end of the order (a missing
+    /// index is rebuilt first, so existing keys keep their place).
+    pub fn append(&self, address: &str, file: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut entries = match self.load()? {
+            Some(entries) => entries,
+            None => self.rebuild()?.0,
+        };
+        if entries.iter().any(|e| e.file == file) {
+            return self.save(entries);
+        }
+        let seq = entries.last().map(|e| e.seq + 1).unwrap_or(0);
+        entries.push(IndexEntry {
+            seq,
+            address: address.to_string(),
+            file: file.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        });
+        self.save(entries)
+    }
+
+    /// Rebuild the index from the key files on disk: entries whose file is
+    /// gone are dropped, unindexed files are appended oldest mtime first.
+    pub fn repair(&self) -> Result<RepairReport> {
+        let _guard = self.lock.lock().unwrap();
+        let (entries, report) = self.rebuild()?;
+        self.save(entries)?;
+        Ok(report)
+    }
+
+    fn rebuild(&self) -> Result<(Vec<IndexEntry>, RepairReport)> {
+        let files = key_files(&self.dir)?;
+        let on_disk: HashSet<&str> = files.iter().map(|f| f.file.as_str()).collect();
+
+        let mut report = RepairReport::default();
+        let mut entries = Vec::new();
+        for e in self.load()?.unwrap_or_default() {
+            if on_disk.contains(e.file.as_str()) {
+                entries.push(e);
+            } else {
+                tracing::warn!("Dropping index entry for missing {}", e.file);
+                report.dropped += 1;
+            }
+        }
+        report.kept = entries.len();
+
+        let indexed: HashSet<String> = entries.iter().map(|e| e.file.clone()).collect();
+        for f in files.into_iter().filter(|f| !indexed.contains(&f.file)) {
+            entries.push(IndexEntry {
+                seq: 0,
+                address: f.address,
+                file: f.file,
+                created_at: chrono::DateTime::<chrono::Utc>::from(f.modified).to_rfc3339(),
+            });
+            report.added += 1;
+        }
+
+        for (seq, e) in entries.iter_mut().enumerate() {
+            e.seq = seq as u64;
+        }
+        Ok((entries, report))
+    }
+
+    fn save(&self, entries: Vec<IndexEntry>) -> Result<()> {
+        write_atomic(&self.path, &serde_json::to_vec_pretty(&IndexFile { entries })?)
+    }
+}
+
+/// A key file found on disk.
+pub struct KeyFile {
+    pub file: String,
+    pub address: String,
+    pub modified: SystemTime,
+}
+
+/// Key files (JSON with an address and a public key), oldest mtime first.
+pub fn key_files<P: AsRef<Path>>(keystore_dir: P) -> Result<Vec<KeyFile>> {
+    let mut out = Vec::new();
+    for entry in fs::read_dir(keystore_dir)? {
+        let e = entry?;
+        let path = e.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let Ok(data) = fs::read_to_string(&path) else { continue };
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(&data) else { continue };
+        let (Some(address), Some(_)) = (
+            v.get("address").and_then(|x| x.as_str()),
+            v.get("pubkey_hex").and_then(|x| x.as_str()),
+        ) else {
+            continue;
+        };
+        out.push(KeyFile {
+            file: e.file_name().to_string_lossy().into_owned(),
+            address: address.to_string(),
+            modified: e.metadata()?.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    out.sort_by_key(|f| f.modified);
+    Ok(out)
+}
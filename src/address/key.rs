@@ -0,0 +1,55 @@
+use anyhow::{bail, Result};
+use ed25519_dalek::hazmat::{raw_sign, ExpandedSecretKey};
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use sha2::Sha512;
+
+/// Ed25519 secret key of an address.
+#[derive(Clone)]
+pub enum PrivateKey {
+    /// RFC 8032 32-byte seed (randomly generated keys).
+    Seed([u8; 32]),
+    /// BIP32-Ed25519 extended key `kL || kR` (HD-derived keys). `kL` is
+    /// used as the scalar as-is, so it cannot be turned back into a seed.
+    Extended([u8; 64]),
+}
+
+impl PrivateKey {
+    /// Parse the raw bytes stored in the keystore (32 or 64 bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes.len() {
+            32 => Ok(Self::Seed(bytes.try_into()?)),
+            64 => Ok(Self::Extended(bytes.try_into()?)),
+            n => bail!("private key must be 32 or 64 bytes, got {n}"),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Seed(sk) => sk.to_vec(),
+            Self::Extended(xsk) => xsk.to_vec(),
+        }
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        match self {
+            Self::Seed(sk) => SigningKey::from_bytes(sk).verifying_key(),
+            Self::Extended(xsk) => VerifyingKey::from(&ExpandedSecretKey::from_bytes(xsk)),
+        }
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.verifying_key().to_bytes()
+    }
+
+    /// Ed25519 signature over `message`.
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        match self {
+            Self::Seed(sk) => SigningKey::from_bytes(sk).sign(message).to_bytes(),
+            Self::Extended(xsk) => {
+                let esk = ExpandedSecretKey::from_bytes(xsk);
+                let vk = VerifyingKey::from(&esk);
+                raw_sign::<Sha512>(&esk, message, &vk).to_bytes()
+            }
+        }
+    }
+}
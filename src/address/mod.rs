@@ -1,19 +1,24 @@
 use anyhow::Result;
 
+pub use key::PrivateKey;
+pub use stake::{AddressType, StakeCredential};
+
 #[derive(Clone)]
 pub struct AddressBundle {
     pub address: String,
     pub pubkey: [u8; 32],
-    pub privkey: [u8; 32],
+    pub privkey: PrivateKey,
     pub address_raw: Vec<u8>,
+    /// Stake key hash of a base address (`None` for enterprise addresses).
+    pub stake_key_hash: Option<[u8; 28]>,
 }
 
 pub trait AddressProvider: Send + Sync {
     /// Generate a new address (baseline capability).
     fn new_address(&self) -> Result<AddressBundle>;
 
-    /// Sign raw message bytes with the 32-byte private key.
-    fn sign_message_raw(&self, privkey: &[u8; 32], message: &str) -> Result<[u8; 64]>;
+    /// Sign raw message bytes with the address' private key.
+    fn sign_message_raw(&self, privkey: &PrivateKey, message: &str) -> Result<[u8; 64]>;
 
     /// Optional: current rotation index among a prefilled pool (default: 0).
     fn current_index(&self) -> usize {
@@ -39,5 +44,11 @@ pub trait AddressProvider: Send + Sync {
 }
 
 // Re-export concrete providers
+pub mod envelope;
+pub mod hd;
+pub mod index;
+pub mod key;
+pub mod sealed;
+pub mod stake;
 pub mod shelley;
 pub mod prefill;
\ No newline at end of file
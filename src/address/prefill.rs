@@ -1,9 +1,13 @@
-use anyhow::Result;
-use crate::address::{AddressProvider, AddressBundle};
+use anyhow::{Context, Result};
+use crate::address::index::{key_files, KeyIndex};
+use crate::address::sealed::{read_secret, KeystoreCipher};
+use crate::address::stake::{HEADER_BASE, HEADER_ENTERPRISE};
+use crate::address::{AddressProvider, AddressBundle, AddressType, PrivateKey};
 use crate::util::bech::bech32_decode_to_bytes;
 
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
@@ -14,55 +18,61 @@ pub struct PrefillProvider<P: AddressProvider + Clone> {
 }
 
 impl<P: AddressProvider + Clone> PrefillProvider<P> {
-    pub fn new(inner: P, keystore_dir: &str) -> Result<Self> {
-        let mut entries: Vec<(std::time::SystemTime, PathBuf)> = Vec::new();
-
-        for entry in fs::read_dir(keystore_dir)? {
-            let e = entry?;
-            let path = e.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                if let Ok(meta) = e.metadata() {
-                    let t = meta.modified().unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-                    entries.push((t, path));
+    pub fn new(inner: P, keystore_dir: &str, cipher: Option<&KeystoreCipher>) -> Result<Self> {
+        // Canonical order comes from the keystore index; files it does not
+        // know (or all of them, without an index) follow by mtime
+        let mut files = key_files(keystore_dir)?;
+        match KeyIndex::new(keystore_dir).load()? {
+            Some(entries) => {
+                let rank: HashMap<&str, u64> = entries.iter().map(|e| (e.file.as_str(), e.seq)).collect();
+                let unindexed = files.iter().filter(|f| !rank.contains_key(f.file.as_str())).count();
+                if unindexed > 0 {
+                    tracing::warn!(
+                        "{} key files are missing from {} — run `repair-index`",
+                        unindexed,
+                        KeyIndex::FILE
+                    );
                 }
+                files.sort_by_key(|f| rank.get(f.file.as_str()).copied().unwrap_or(u64::MAX));
             }
+            None if !files.is_empty() => {
+                tracing::warn!("No {} in keystore, ordering keys by mtime — run `repair-index`", KeyIndex::FILE);
+            }
+            None => {}
         }
 
-        entries.sort_by_key(|(t, _)| *t);
-
         let mut list = Vec::new();
 
-        for (_, path) in entries {
-            if let Ok(data) = fs::read_to_string(&path) {
-                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&data) {
-                    if let (Some(addr), Some(pk), Some(sk)) = (
-                        v.get("address").and_then(|x| x.as_str()),
-                        v.get("pubkey_hex").and_then(|x| x.as_str()),
-                        v.get("privkey_hex").and_then(|x| x.as_str()),
-                    ) {
-                        if let (Ok(pubkey), Ok(privkey)) = (hex::decode(pk), hex::decode(sk)) {
-                            if pubkey.len() == 32 && privkey.len() == 32 {
-                                let mut pk32 = [0u8; 32];
-                                let mut sk32 = [0u8; 32];
-                                pk32.copy_from_slice(&pubkey);
-                                sk32.copy_from_slice(&privkey);
-
-                                list.push(AddressBundle {
-                                    address: addr.to_string(),
-                                    pubkey: pk32,
-                                    privkey: sk32,
-                                    address_raw: bech32_decode_to_bytes(addr),
-                                });
-                            }
-                        }
-                    }
+        for f in files {
+            let path = Path::new(keystore_dir).join(&f.file);
+            let v = match fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| Ok(serde_json::from_str::<serde_json::Value>(&data)?))
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    tracing::warn!("Skipping {}: {e}", path.display());
+                    continue;
                 }
+            };
+            // A key that fails to decrypt (wrong passphrase, tampered file)
+            // aborts the start instead of silently mining with fewer keys
+            let Some(privkey) = read_secret(&v, "privkey_hex", cipher).with_context(|| path.display().to_string())?
+            else {
+                continue;
+            };
+            match bundle(&v, &privkey) {
+                Ok(b) => list.push(b),
+                Err(e) => tracing::warn!("Skipping {}: {e}", path.display()),
             }
         }
 
-        println!("Loaded {} existing addresses (oldest first)", list.len());
+        // Addresses the inner provider can regenerate (HD keystore)
+        list.extend(inner.all_addresses()?);
+
+        tracing::info!("Loaded {} existing addresses (keystore order)", list.len());
         for (i, a) in list.iter().enumerate() {
-            println!("  {}. {}", i + 1, a.address);
+            tracing::debug!("  {}. {}", i + 1, a.address);
         }
 
         Ok(Self {
@@ -73,13 +83,58 @@ impl<P: AddressProvider + Clone> PrefillProvider<P> {
     }
 }
 
+/// Address bundle of a key file with private key `privkey`.
+fn bundle(v: &serde_json::Value, privkey: &[u8]) -> Result<AddressBundle> {
+    let address = v.get("address").and_then(|x| x.as_str()).context("no address")?;
+    let pubkey = hex::decode(v.get("pubkey_hex").and_then(|x| x.as_str()).context("no pubkey_hex")?)?;
+    let pubkey = <[u8; 32]>::try_from(pubkey).map_err(|_| anyhow::anyhow!("pubkey_hex must be 32 bytes"))?;
+
+    let address_raw = bech32_decode_to_bytes(address);
+    let stake_key_hash = recorded_stake(v, &address_raw)?;
+    Ok(AddressBundle {
+        address: address.to_string(),
+        pubkey,
+        privkey: PrivateKey::from_bytes(privkey)?,
+        address_raw,
+        stake_key_hash,
+    })
+}
+
+/// Stake key hash recorded in a key file, checked against its address.
+fn recorded_stake(v: &serde_json::Value, address_raw: &[u8]) -> Result<Option<[u8; 28]>> {
+    let address_type: AddressType = match v.get("address_type") {
+        Some(t) => serde_json::from_value(t.clone())?,
+        None => AddressType::Enterprise, // written before base addresses existed
+    };
+    let skh = v
+        .get("stake_key_hash")
+        .and_then(|x| x.as_str())
+        .map(hex::decode)
+        .transpose()?;
+
+    let header = address_raw.first().map(|h| h >> 4);
+    match (address_type, skh) {
+        (AddressType::Enterprise, None)
+            if header == Some(HEADER_ENTERPRISE) && address_raw.len() == 29 => Ok(None),
+        (AddressType::Base, Some(skh))
+            if header == Some(HEADER_BASE) && address_raw.len() == 57 && address_raw[29..] == skh[..] =>
+        {
+            Ok(Some(skh.try_into().unwrap()))
+        }
+        _ => anyhow::bail!("address does not match its recorded type {:?}", address_type),
+    }
+}
+
 impl<P: AddressProvider + Clone> AddressProvider for PrefillProvider<P> {
     fn new_address(&self) -> Result<AddressBundle> {
-        // Fall back to delegating to the inner provider
-        self.inner.new_address()
+        // Delegate to the inner provider, and keep the key in rotation (and
+        // in `all_addresses`) for the rest of the session
+        let a = self.inner.new_address()?;
+        self.list.lock().unwrap().push(a.clone());
+        Ok(a)
     }
 
-    fn sign_message_raw(&self, privkey: &[u8; 32], message: &str) -> Result<[u8; 64]> {
+    fn sign_message_raw(&self, privkey: &PrivateKey, message: &str) -> Result<[u8; 64]> {
         self.inner.sign_message_raw(privkey, message)
     }
 
@@ -96,7 +151,9 @@ impl<P: AddressProvider + Clone> AddressProvider for PrefillProvider<P> {
 
         if list.is_empty() {
             // No prefilled keys — generate a fresh one
-            return self.inner.new_address();
+            let a = self.inner.new_address()?;
+            list.push(a.clone());
+            return Ok(a);
         }
 
         let mut i = self.index.lock().unwrap();
@@ -104,11 +161,31 @@ impl<P: AddressProvider + Clone> AddressProvider for PrefillProvider<P> {
         *i = (*i + 1) % list.len();
 
         let a = list[idx].clone();
-        println!("Using existing address (rr index {}): {}", idx, a.address);
+        tracing::debug!("Using existing address (rr index {}): {}", idx, a.address);
         Ok(a)
     }
 
     fn all_addresses(&self) -> Result<Vec<AddressBundle>> {
         Ok(self.list.lock().unwrap().clone())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::shelley::ShelleyProvider;
+    use crate::Network;
+
+    #[tokio::test]
+    async fn new_addresses_join_the_rotation() {
+        let dir = std::env::temp_dir().join(format!("scavenger-prefill-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        let shelley = ShelleyProvider::new(Network::Preprod, dir, None).await.unwrap();
+        let provider = PrefillProvider::new(shelley, dir, None).unwrap();
+
+        let first = provider.next_address().unwrap();
+        let second = provider.new_address().unwrap();
+        let all: Vec<String> = provider.all_addresses().unwrap().into_iter().map(|a| a.address).collect();
+        assert_eq!(all, [first.address, second.address]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
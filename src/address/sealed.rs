@@ -0,0 +1,423 @@
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Header of an encrypted keystore: keystore/00keystore.json
+pub const HEADER_FILE: &str = "00keystore.json";
+
+/// Associated data of the HD root secret (00hd.json).
+pub const HD_ROOT_AAD: &[u8] = b"hd-root";
+
+const FORMAT_VERSION: u32 = 1;
+const VERIFIER: &[u8] = b"scavenger-miner keystore";
+
+/// Plaintext secret fields and their encrypted counterparts.
+const SECRET_FIELDS: [(&str, &str); 3] = [
+    ("privkey_hex", "privkey_enc"),
+    ("stake_privkey_hex", "stake_privkey_enc"),
+    ("root_xprv", "root_xprv_enc"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    alg: String,
+    m_cost_kib: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+}
+
+impl KdfParams {
+    fn argon2id() -> Self {
+        let mut salt = [0u8; 16];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        Self {
+            alg: "argon2id".to_string(),
+            m_cost_kib: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+            salt: hex::encode(salt),
+        }
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<[u8; 32]> {
+        if self.alg != "argon2id" {
+            bail!("unsupported keystore KDF {}", self.alg);
+        }
+        let params = Params::new(self.m_cost_kib, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| anyhow!("invalid argon2 parameters: {e}"))?;
+        let salt = hex::decode(&self.salt)?;
+
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("argon2: {e}"))?;
+        Ok(key)
+    }
+}
+
+/// Contents of the keystore header.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    kdf: KdfParams,
+    cipher: String,
+    /// A known value sealed with the key, to tell a wrong passphrase apart
+    /// from a damaged key file.
+    verifier: Sealed,
+}
+
+/// One encrypted secret as stored in a key file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sealed {
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// Key derived from the keystore passphrase.
+pub struct KeystoreCipher {
+    aead: XChaCha20Poly1305,
+}
+
+impl KeystoreCipher {
+    /// Whether `keystore_dir` has been encrypted.
+    pub fn is_encrypted<P: AsRef<Path>>(keystore_dir: P) -> bool {
+        keystore_dir.as_ref().join(HEADER_FILE).exists()
+    }
+
+    /// Write a new header for `keystore_dir` and return its cipher.
+    pub fn create<P: AsRef<Path>>(keystore_dir: P, passphrase: &str) -> Result<Self> {
+        let path = keystore_dir.as_ref().join(HEADER_FILE);
+        if path.exists() {
+            bail!("{} already exists", path.display());
+        }
+
+        let kdf = KdfParams::argon2id();
+        let cipher = Self::from_key(&kdf.derive_key(passphrase)?);
+        let header = Header {
+            version: FORMAT_VERSION,
+            kdf,
+            cipher: "xchacha20poly1305".to_string(),
+            verifier: cipher.seal(VERIFIER, HEADER_FILE.as_bytes()),
+        };
+
+        write_atomic(&path, &serde_json::to_vec_pretty(&header)?)?;
+        Ok(cipher)
+    }
+
+    /// Derive the key for an encrypted `keystore_dir` and check the passphrase.
+    pub fn open<P: AsRef<Path>>(keystore_dir: P, passphrase: &str) -> Result<Self> {
+        let path = keystore_dir.as_ref().join(HEADER_FILE);
+        let data = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        let header: Header = serde_json::from_str(&data).with_context(|| format!("parsing {}", path.display()))?;
+
+        if header.version != FORMAT_VERSION {
+            bail!("unsupported keystore version {}", header.version);
+        }
+        if header.cipher != "xchacha20poly1305" {
+            bail!("unsupported keystore cipher {}", header.cipher);
+        }
+
+        let cipher = Self::from_key(&header.kdf.derive_key(passphrase)?);
+        match cipher.unseal(&header.verifier, HEADER_FILE.as_bytes()) {
+            Ok(v) if v == VERIFIER => Ok(cipher),
+            _ => bail!("wrong keystore passphrase"),
+        }
+    }
+
+    fn from_key(key: &[u8; 32]) -> Self {
+        Self {
+            aead: XChaCha20Poly1305::new(key.into()),
+        }
+    }
+
+    /// Encrypt `secret`; `aad` binds it to its owner (e.g. the address).
+    pub fn seal(&self, secret: &[u8], aad: &[u8]) -> Sealed {
+        let mut nonce = [0u8; 24];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .aead
+            .encrypt(&XNonce::from(nonce), Payload { msg: secret, aad })
+            .expect("XChaCha20Poly1305 encryption cannot fail");
+        Sealed {
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        }
+    }
+
+    pub fn unseal(&self, sealed: &Sealed, aad: &[u8]) -> Result<Vec<u8>> {
+        let nonce: [u8; 24] = hex::decode(&sealed.nonce)?
+            .try_into()
+            .map_err(|_| anyhow!("nonce must be 24 bytes"))?;
+        let ciphertext = hex::decode(&sealed.ciphertext)?;
+        self.aead
+            .decrypt(&XNonce::from(nonce), Payload { msg: &ciphertext, aad })
+            .map_err(|_| anyhow!("decryption failed (damaged or foreign key file)"))
+    }
+}
+
+/// Passphrase from `file`, env KEYSTORE_PASSPHRASE or an interactive prompt
+/// (asked twice when `confirm` is set).
+pub fn read_passphrase(file: Option<&Path>, confirm: bool) -> Result<String> {
+    if let Some(file) = file {
+        let s = fs::read_to_string(file).with_context(|| format!("reading {}", file.display()))?;
+        return Ok(s.trim_end_matches(['\r', '\n']).to_string());
+    }
+    if let Ok(s) = std::env::var("KEYSTORE_PASSPHRASE") {
+        return Ok(s);
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("keystore passphrase required: set KEYSTORE_PASSPHRASE or --passphrase-file");
+    }
+
+    let pass = rpassword::prompt_password("Keystore passphrase: ")?;
+    if confirm && rpassword::prompt_password("Repeat passphrase: ")? != pass {
+        bail!("passphrases do not match");
+    }
+    Ok(pass)
+}
+
+/// Key files in `keystore_dir` holding a plaintext or an encrypted secret.
+fn scan<P: AsRef<Path>>(keystore_dir: P) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let (mut plain, mut sealed) = (Vec::new(), Vec::new());
+    for entry in fs::read_dir(keystore_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let Ok(v) = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|d| Ok(serde_json::from_str::<serde_json::Value>(&d)?))
+        else {
+            continue;
+        };
+        if SECRET_FIELDS.iter().any(|(p, _)| v.get(p).is_some()) {
+            plain.push(path);
+        } else if SECRET_FIELDS.iter().any(|(_, s)| v.get(s).is_some()) {
+            sealed.push(path);
+        }
+    }
+    Ok((plain, sealed))
+}
+
+/// Unlock `keystore_dir` for the miner.
+///
+/// Returns `None` for a plaintext keystore. An encrypted keystore that
+/// still contains plaintext keys is refused unless `allow_mixed` is set.
+pub fn unlock<P: AsRef<Path>>(
+    keystore_dir: P,
+    passphrase_file: Option<&Path>,
+    allow_mixed: bool,
+) -> Result<Option<Arc<KeystoreCipher>>> {
+    let dir = keystore_dir.as_ref();
+    if !dir.exists() {
+        return Ok(None);
+    }
+    let (plain, sealed) = scan(dir)?;
+
+    if !KeystoreCipher::is_encrypted(dir) {
+        if !sealed.is_empty() {
+            bail!(
+                "{} encrypted key files but no {} in {}",
+                sealed.len(),
+                HEADER_FILE,
+                dir.display()
+            );
+        }
+        return Ok(None);
+    }
+
+    if !plain.is_empty() {
+        if !allow_mixed {
+            bail!(
+                "keystore mixes {} plaintext and {} encrypted key files (first: {}); \
+                 run `encrypt-keystore` or pass --allow-mixed-keystore",
+                plain.len(),
+                sealed.len(),
+                plain[0].display()
+            );
+        }
+        tracing::warn!("Keystore still holds {} plaintext key files", plain.len());
+    }
+
+    let pass = read_passphrase(passphrase_file, false)?;
+    Ok(Some(Arc::new(KeystoreCipher::open(dir, &pass)?)))
+}
+
+/// Encrypt every plaintext key file in `keystore_dir` in place, creating
+/// the header on first use. Returns the number of files migrated.
+pub fn encrypt_keystore<P: AsRef<Path>>(keystore_dir: P, passphrase_file: Option<&Path>) -> Result<usize> {
+    let dir = keystore_dir.as_ref();
+    let cipher = if KeystoreCipher::is_encrypted(dir) {
+        KeystoreCipher::open(dir, &read_passphrase(passphrase_file, false)?)?
+    } else {
+        KeystoreCipher::create(dir, &read_passphrase(passphrase_file, true)?)?
+    };
+
+    let (plain, _) = scan(dir)?;
+    for path in &plain {
+        let data = fs::read_to_string(path)?;
+        let mut v: serde_json::Value = serde_json::from_str(&data)?;
+        let obj = v.as_object_mut().context("key file is not a JSON object")?;
+
+        for (plain_field, sealed_field) in SECRET_FIELDS {
+            let Some(secret) = obj.remove(plain_field) else { continue };
+            let secret = hex::decode(secret.as_str().context("secret is not a hex string")?)?;
+            let aad = secret_aad(obj);
+            obj.insert(sealed_field.to_string(), serde_json::to_value(cipher.seal(&secret, &aad))?);
+        }
+
+        write_atomic(path, &serde_json::to_vec_pretty(&v)?)?;
+        tracing::info!("Encrypted {}", path.display());
+    }
+    Ok(plain.len())
+}
+
+/// Secret `field` (plaintext name, e.g. `privkey_hex`) of a key file,
+/// decrypted if sealed; `None` if the file has no such secret.
+pub fn read_secret(
+    v: &serde_json::Value,
+    field: &str,
+    cipher: Option<&KeystoreCipher>,
+) -> Result<Option<Vec<u8>>> {
+    let sealed_field = SECRET_FIELDS
+        .iter()
+        .find(|(p, _)| *p == field)
+        .map(|(_, s)| *s)
+        .context("unknown secret field")?;
+    let obj = v.as_object().context("key file is not a JSON object")?;
+
+    if let Some(secret) = obj.get(field) {
+        return Ok(Some(hex::decode(secret.as_str().context("secret is not a hex string")?)?));
+    }
+    let Some(enc) = obj.get(sealed_field) else {
+        return Ok(None);
+    };
+    let Some(cipher) = cipher else {
+        bail!("key file is encrypted but the keystore is not unlocked");
+    };
+    let sealed: Sealed = serde_json::from_value(enc.clone())?;
+    Ok(Some(cipher.unseal(&sealed, &secret_aad(obj))?))
+}
+
+/// Associated data for a key file secret: its address, or a fixed label
+/// for the HD root.
+pub fn secret_aad(obj: &serde_json::Map<String, serde_json::Value>) -> Vec<u8> {
+    match obj.get("address").and_then(|a| a.as_str()) {
+        Some(addr) => addr.as_bytes().to_vec(),
+        None => HD_ROOT_AAD.to_vec(),
+    }
+}
+
+/// Write-then-rename so a crash never leaves a truncated file.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let mut f = fs::File::create(&tmp)?;
+    f.write_all(data)?;
+    f.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scavenger-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cipher() -> KeystoreCipher {
+        KeystoreCipher::from_key(&[7; 32])
+    }
+
+    #[test]
+    fn seal_unseal_round_trip() {
+        let c = cipher();
+        let sealed = c.seal(b"secret key", b"addr_test1");
+        assert_eq!(c.unseal(&sealed, b"addr_test1").unwrap(), b"secret key");
+        // Bound to its owner
+        assert!(c.unseal(&sealed, b"addr_test2").is_err());
+    }
+
+    #[test]
+    fn tampered_ciphertext_or_nonce_is_rejected() {
+        let c = cipher();
+        let sealed = c.seal(b"secret key", b"addr");
+        let flip = |hex_str: &str| {
+            let mut b = hex::decode(hex_str).unwrap();
+            b[0] ^= 1;
+            hex::encode(b)
+        };
+
+        let bad_ct = Sealed { ciphertext: flip(&sealed.ciphertext), ..sealed.clone() };
+        assert!(c.unseal(&bad_ct, b"addr").is_err());
+        let bad_nonce = Sealed { nonce: flip(&sealed.nonce), ..sealed.clone() };
+        assert!(c.unseal(&bad_nonce, b"addr").is_err());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_an_error() {
+        let dir = temp_dir("sealed-pass");
+        let created = KeystoreCipher::create(&dir, "right horse").unwrap();
+        let sealed = created.seal(b"secret key", b"addr");
+
+        let err = KeystoreCipher::open(&dir, "wrong horse").err().unwrap();
+        assert!(err.to_string().contains("wrong keystore passphrase"), "{err}");
+
+        let opened = KeystoreCipher::open(&dir, "right horse").unwrap();
+        assert_eq!(opened.unseal(&sealed, b"addr").unwrap(), b"secret key");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_secret_plain_and_sealed() {
+        let c = cipher();
+        let plain = json!({ "address": "addr_test1", "privkey_hex": "0a0b" });
+        assert_eq!(read_secret(&plain, "privkey_hex", None).unwrap(), Some(vec![0x0a, 0x0b]));
+        assert_eq!(read_secret(&plain, "stake_privkey_hex", None).unwrap(), None);
+
+        let sealed = json!({
+            "address": "addr_test1",
+            "privkey_enc": c.seal(&[0x0a, 0x0b], b"addr_test1"),
+        });
+        assert_eq!(read_secret(&sealed, "privkey_hex", Some(&c)).unwrap(), Some(vec![0x0a, 0x0b]));
+        assert!(read_secret(&sealed, "privkey_hex", None).is_err());
+        assert!(read_secret(&sealed, "privkey_hex", Some(&KeystoreCipher::from_key(&[8; 32]))).is_err());
+    }
+
+    #[test]
+    fn encrypt_keystore_is_idempotent() {
+        let dir = temp_dir("sealed-migrate");
+        let pass_file = dir.join("passphrase.txt");
+        fs::write(&pass_file, "right horse\n").unwrap();
+        let key_file = dir.join("addr_test1.json");
+        let original = json!({ "address": "addr_test1", "pubkey_hex": "00", "privkey_hex": "0a0b" });
+        fs::write(&key_file, serde_json::to_vec(&original).unwrap()).unwrap();
+
+        assert_eq!(encrypt_keystore(&dir, Some(&pass_file)).unwrap(), 1);
+        let migrated = fs::read_to_string(&key_file).unwrap();
+        let v: serde_json::Value = serde_json::from_str(&migrated).unwrap();
+        assert!(v.get("privkey_hex").is_none());
+        assert_eq!(v["pubkey_hex"], "00");
+
+        let c = KeystoreCipher::open(&dir, "right horse").unwrap();
+        assert_eq!(read_secret(&v, "privkey_hex", Some(&c)).unwrap(), Some(vec![0x0a, 0x0b]));
+
+        // Second run finds nothing left to migrate and leaves the file alone
+        assert_eq!(encrypt_keystore(&dir, Some(&pass_file)).unwrap(), 0);
+        assert_eq!(fs::read_to_string(&key_file).unwrap(), migrated);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,8 +1,16 @@
-use anyhow::Result;
-use ed25519_dalek::{SigningKey, VerifyingKey, Signer};
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::SigningKey;
 
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 use tokio::fs;
-use crate::address::{AddressBundle, AddressProvider};
+use crate::address::envelope::{CardanoKey, ExportedAddress};
+use crate::address::hd::HdWallet;
+use crate::address::index::{key_files, KeyIndex};
+use crate::address::sealed::{read_secret, write_atomic, KeystoreCipher};
+use crate::address::stake::address_bytes;
+use crate::address::{AddressBundle, AddressProvider, AddressType, PrivateKey, StakeCredential};
 use crate::util::bech::{blake2b224, bech32_encode};
 use crate::Network;
 
@@ -11,66 +19,217 @@ pub struct ShelleyProvider {
     hrp: String,
     network_id: u8,
     keystore_dir: String,
+    /// Present when the keystore holds an HD root (00hd.json): addresses
+    /// are derived instead of generated randomly.
+    hd: Option<Arc<HdWallet>>,
+    /// Encrypts new key files when the keystore is encrypted.
+    cipher: Option<Arc<KeystoreCipher>>,
+    index: Arc<KeyIndex>,
+    /// Stake credential of new random-key addresses (`None`: enterprise).
+    stake: Option<StakeCredential>,
 }
 
 impl ShelleyProvider {
-    pub async fn new(network: Network, keystore_dir: &str) -> Result<Self> {
+    pub async fn new(
+        network: Network,
+        keystore_dir: &str,
+        cipher: Option<Arc<KeystoreCipher>>,
+    ) -> Result<Self> {
         fs::create_dir_all(keystore_dir).await.ok();
+
+        let hd = HdWallet::load(keystore_dir, cipher.clone())?.map(Arc::new);
+        if let Some(hd) = &hd {
+            tracing::info!(
+                "HD keystore: deriving addresses m/1852'/1815'/0'/0/i ({} in use)",
+                hd.next_index()
+            );
+        }
+
         Ok(Self {
             hrp: network.bech32_hrp().to_string(),
             network_id: network.network_id(),
             keystore_dir: keystore_dir.to_string(),
+            hd,
+            cipher,
+            index: Arc::new(KeyIndex::new(keystore_dir)),
+            stake: None,
         })
     }
-}
 
-impl AddressProvider for ShelleyProvider {
-    fn new_address(&self) -> Result<AddressBundle> {
-        use rand::rngs::OsRng;
-        let mut rng = OsRng;
+    /// Generate base addresses with `stake` instead of enterprise ones
+    /// (`None`). An HD keystore keeps the choice made at `hd-init`.
+    pub fn with_stake(mut self, stake: Option<StakeCredential>) -> Self {
+        if let Some(hd) = &self.hd {
+            if hd.stake() != stake.as_ref() {
+                tracing::warn!(
+                    "HD keystore was created with stake credential {:?}; ignoring {:?}",
+                    hd.stake(),
+                    stake
+                );
+            }
+        }
+        self.stake = stake;
+        self
+    }
 
-        let signing = SigningKey::generate(&mut rng);
-        let verifying: VerifyingKey = (&signing).into();
+    fn bundle(&self, privkey: PrivateKey, stake_key_hash: Option<[u8; 28]>) -> AddressBundle {
+        let pk = privkey.public_key();
+        let raw = address_bytes(self.network_id, &pk, stake_key_hash.as_ref());
 
-        let pk = verifying.to_bytes();
-        let sk = signing.to_bytes();
+        AddressBundle {
+            address: bech32_encode(&self.hrp, &raw),
+            pubkey: pk,
+            privkey,
+            address_raw: raw,
+            stake_key_hash,
+        }
+    }
+
+    fn hd_bundle(&self, hd: &HdWallet, index: u32) -> Result<AddressBundle> {
+        Ok(self.bundle(hd.derive(index), hd.stake_key_hash(index)?))
+    }
 
-        // Build raw address
-        let header: u8 = (0b0110 << 4) | (self.network_id & 0x0f);
-        let pkh = blake2b224(&pk);
+    /// Write a key file for `privkey` (and its own `stake_key`, if any) and
+    /// append it to the index.
+    fn store(&self, privkey: PrivateKey, stake_key: Option<PrivateKey>) -> Result<AddressBundle> {
+        let stake_key_hash = match (&self.stake, &stake_key) {
+            (_, Some(sk)) => Some(blake2b224(&sk.public_key())),
+            (Some(StakeCredential::PerAddress), None) => bail!("per-address stake credential without a stake key"),
+            (Some(shared), None) => shared.shared_hash()?,
+            (None, None) => None,
+        };
 
-        let mut raw = Vec::new();
-        raw.push(header);
-        raw.extend_from_slice(&pkh);
+        let bundle = self.bundle(privkey, stake_key_hash);
+        let address_type = match stake_key_hash {
+            Some(_) => AddressType::Base,
+            None => AddressType::Enterprise,
+        };
 
-        let address = bech32_encode(&self.hrp, &raw);
+        let file = format!("{}.json", hex::encode(bundle.pubkey));
+        let path = Path::new(&self.keystore_dir).join(&file);
+        if path.exists() {
+            bail!("this key is already in the keystore ({})", path.display());
+        }
 
         // Persist JSON
-        let rec = serde_json::json!({
-            "address": address,
-            "pubkey_hex": hex::encode(pk),
-            "privkey_hex": hex::encode(sk),
+        let mut rec = serde_json::json!({
+            "address": bundle.address,
+            "pubkey_hex": hex::encode(bundle.pubkey),
+            "address_type": address_type,
         });
+        if let Some(skh) = stake_key_hash {
+            rec["stake_key_hash"] = hex::encode(skh).into();
+        }
 
-        let path = format!("{}/{}.json", self.keystore_dir, hex::encode(pk));
-        std::fs::write(path, serde_json::to_vec_pretty(&rec).unwrap())?;
+        let aad = bundle.address.as_bytes();
+        let secret = bundle.privkey.to_bytes();
+        match &self.cipher {
+            Some(cipher) => {
+                rec["privkey_enc"] = serde_json::to_value(cipher.seal(&secret, aad))?;
+                if let Some(sk) = &stake_key {
+                    rec["stake_privkey_enc"] = serde_json::to_value(cipher.seal(&sk.to_bytes(), aad))?;
+                }
+            }
+            None => {
+                rec["privkey_hex"] = hex::encode(&secret).into();
+                if let Some(sk) = &stake_key {
+                    rec["stake_privkey_hex"] = hex::encode(sk.to_bytes()).into();
+                }
+            }
+        }
 
-        Ok(AddressBundle {
-            address,
-            pubkey: pk,
-            privkey: sk,
-            address_raw: raw,
-        })
+        write_atomic(&path, &serde_json::to_vec_pretty(&rec)?)?;
+        self.index.append(&bundle.address, &file)?;
+
+        Ok(bundle)
     }
 
-    fn sign_message_raw(&self, privkey: &[u8; 32], message: &str) -> Result<[u8; 64]> {
-        let sk = SigningKey::from_bytes(privkey);
-        let sig = sk.sign(message.as_bytes());
-        Ok(sig.to_bytes())
+    /// Add an existing key to the keystore. With `stake_key` the address is
+    /// a base address with that stake key; otherwise the configured stake
+    /// credential applies (a per-address one needs `stake_key`).
+    pub fn import_key(&self, privkey: PrivateKey, stake_key: Option<PrivateKey>) -> Result<AddressBundle> {
+        if stake_key.is_none() && self.stake == Some(StakeCredential::PerAddress) {
+            bail!("importing a base address with its own stake key needs the stake signing key");
+        }
+        self.store(privkey, stake_key)
     }
 
-    /// Shelley provider does NOT store addresses — only Prefill does.
+    /// Every key of the keystore: key files in index order, then the HD
+    /// addresses.
+    pub fn export_keys(&self) -> Result<Vec<ExportedAddress>> {
+        let mut files = key_files(&self.keystore_dir)?;
+        if let Some(entries) = self.index.load()? {
+            let rank: HashMap<&str, u64> = entries.iter().map(|e| (e.file.as_str(), e.seq)).collect();
+            files.sort_by_key(|f| rank.get(f.file.as_str()).copied().unwrap_or(u64::MAX));
+        }
+
+        let cipher = self.cipher.as_deref();
+        let mut out = Vec::new();
+        for f in files {
+            let path = Path::new(&self.keystore_dir).join(&f.file);
+            let v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            let secret = |field| -> Result<Option<CardanoKey>> {
+                let Some(bytes) = read_secret(&v, field, cipher).with_context(|| path.display().to_string())?
+                else {
+                    return Ok(None);
+                };
+                Ok(Some(CardanoKey {
+                    key: PrivateKey::from_bytes(&bytes)?,
+                    chain_code: None,
+                }))
+            };
+            let Some(payment) = secret("privkey_hex")? else { continue };
+            out.push(ExportedAddress {
+                address: f.address,
+                payment,
+                stake: secret("stake_privkey_hex")?,
+            });
+        }
+
+        if let Some(hd) = &self.hd {
+            for i in 0..hd.next_index() {
+                out.push(ExportedAddress {
+                    address: self.hd_bundle(hd, i)?.address,
+                    payment: (&hd.payment_xprv(i)).into(),
+                    stake: hd.stake_xprv(i).as_ref().map(CardanoKey::from),
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl AddressProvider for ShelleyProvider {
+    fn new_address(&self) -> Result<AddressBundle> {
+        if let Some(hd) = &self.hd {
+            // Nothing to persist but the index; the key can be re-derived
+            let index = hd.take_index()?;
+            return self.hd_bundle(hd, index);
+        }
+
+        use rand::rngs::OsRng;
+        let mut rng = OsRng;
+
+        let signing = SigningKey::generate(&mut rng);
+
+        // Per-address stake keys are random too and stored alongside
+        let stake_key = match &self.stake {
+            Some(StakeCredential::PerAddress) => Some(PrivateKey::Seed(SigningKey::generate(&mut rng).to_bytes())),
+            _ => None,
+        };
+        self.store(PrivateKey::Seed(signing.to_bytes()), stake_key)
+    }
+
+    fn sign_message_raw(&self, privkey: &PrivateKey, message: &str) -> Result<[u8; 64]> {
+        Ok(privkey.sign(message.as_bytes()))
+    }
+
+    /// Random keys are listed by Prefill from their JSON files; HD
+    /// addresses are re-derived here.
     fn all_addresses(&self) -> Result<Vec<AddressBundle>> {
-        Ok(Vec::new())
+        let Some(hd) = &self.hd else {
+            return Ok(Vec::new());
+        };
+        (0..hd.next_index()).map(|i| self.hd_bundle(hd, i)).collect()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,108 @@
+use anyhow::{bail, Result};
+use bech32::FromBase32;
+use serde::{Deserialize, Serialize};
+
+use crate::util::bech::blake2b224;
+
+/// Shelley address header types (CIP-19).
+pub const HEADER_BASE: u8 = 0b0000;
+pub const HEADER_ENTERPRISE: u8 = 0b0110;
+const HEADER_REWARD_KEY: u8 = 0b1110;
+
+/// Type of the addresses the keystore generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressType {
+    /// Payment key only (header type 6).
+    #[default]
+    Enterprise,
+    /// Payment key plus stake key hash (header type 0).
+    Base,
+}
+
+impl AddressType {
+    pub fn header(&self) -> u8 {
+        match self {
+            AddressType::Enterprise => HEADER_ENTERPRISE,
+            AddressType::Base => HEADER_BASE,
+        }
+    }
+}
+
+/// Where the stake credential of base addresses comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum StakeCredential {
+    /// One stake key hash (hex) shared by every address, e.g. the stake key
+    /// of a delegated wallet.
+    Shared { key_hash: String },
+    /// A separate stake key per address (random keys: stored next to the
+    /// payment key; HD keys: `m/1852'/1815'/0'/2/i`).
+    PerAddress,
+}
+
+impl StakeCredential {
+    pub fn shared(key_hash: [u8; 28]) -> Self {
+        StakeCredential::Shared { key_hash: hex::encode(key_hash) }
+    }
+
+    /// The shared stake key hash, `None` for per-address keys.
+    pub fn shared_hash(&self) -> Result<Option<[u8; 28]>> {
+        match self {
+            StakeCredential::Shared { key_hash } => {
+                let bytes: [u8; 28] = hex::decode(key_hash)?
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("stake key hash must be 28 bytes"))?;
+                Ok(Some(bytes))
+            }
+            StakeCredential::PerAddress => Ok(None),
+        }
+    }
+}
+
+/// Raw Shelley address bytes: header, payment key hash and, for base
+/// addresses, the stake key hash.
+pub fn address_bytes(network_id: u8, payment_pubkey: &[u8; 32], stake_key_hash: Option<&[u8; 28]>) -> Vec<u8> {
+    let kind = match stake_key_hash {
+        Some(_) => AddressType::Base,
+        None => AddressType::Enterprise,
+    };
+    let mut raw = Vec::with_capacity(57);
+    raw.push((kind.header() << 4) | (network_id & 0x0f));
+    raw.extend_from_slice(&blake2b224(payment_pubkey));
+    if let Some(skh) = stake_key_hash {
+        raw.extend_from_slice(skh);
+    }
+    raw
+}
+
+/// Parse a shared stake key given as a key hash (56 hex chars), a stake
+/// verification key (64 hex chars or `stake_vk1…`) or a stake address
+/// (`stake1…` / `stake_test1…`).
+pub fn parse_stake_key_hash(input: &str, network_id: u8) -> Result<[u8; 28]> {
+    let input = input.trim();
+
+    if let Ok(bytes) = hex::decode(input) {
+        return match bytes.len() {
+            28 => Ok(bytes.try_into().unwrap()),
+            32 => Ok(blake2b224(&bytes)),
+            n => bail!("stake key hex must be a 28-byte hash or a 32-byte key, got {n} bytes"),
+        };
+    }
+
+    let (hrp, data, _variant) = bech32::decode(input)?;
+    let bytes = Vec::<u8>::from_base32(&data)?;
+    match hrp.as_str() {
+        "stake_vk" if bytes.len() == 32 => Ok(blake2b224(&bytes)),
+        "stake" | "stake_test" if bytes.len() == 29 => {
+            if bytes[0] >> 4 != HEADER_REWARD_KEY {
+                bail!("stake address must carry a key hash (script credentials are not supported)");
+            }
+            if bytes[0] & 0x0f != network_id {
+                bail!("stake address is for another network");
+            }
+            Ok(bytes[1..].try_into().unwrap())
+        }
+        _ => bail!("unsupported stake key {hrp}1…"),
+    }
+}
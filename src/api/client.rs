@@ -1,11 +1,15 @@
+use super::error::{ApiError, ApiResult};
+use super::retry::RetryPolicy;
 use super::types::*;
-use anyhow::Context;
 use reqwest::Url;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Clone)]
 pub struct ScavengerClient {
     base: Url,
     http: reqwest::Client,
+    retry: RetryPolicy,
 }
 
 impl ScavengerClient {
@@ -19,93 +23,161 @@ impl ScavengerClient {
                 h
             })
             .build()?;
-        Ok(Self { base, http })
+        Ok(Self { base, http, retry: RetryPolicy::default() })
     }
 
-    pub async fn get_tandc(&self, version: Option<&str>) -> anyhow::Result<TandCResponse> {
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Run `call` until it succeeds, fails permanently or attempts run out.
+    async fn retrying<T, F, Fut>(&self, what: &str, mut call: F) -> ApiResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ApiResult<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(e) if attempt < self.retry.max_attempts && self.retry.is_retryable(&e) => {
+                    let delay = self.retry.delay(attempt, &e);
+                    tracing::warn!(
+                        "{} failed (attempt {}/{}): {} — retrying in {:.1?}",
+                        what, attempt, self.retry.max_attempts, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn url(&self, path: &str) -> ApiResult<Url> {
+        self.base
+            .join(path)
+            .map_err(|e| ApiError::InvalidUrl(format!("{path}: {e}")))
+    }
+
+    /// Turn non-success responses into a classified `ApiError`.
+    async fn check(resp: reqwest::Response) -> ApiResult<reqwest::Response> {
+        if resp.status().is_success() {
+            Ok(resp)
+        } else {
+            Err(ApiError::from_response(resp).await)
+        }
+    }
+
+    /// Parse a success response. The body is read first so a connection
+    /// lost mid-body stays a transport error and bad JSON becomes `Decode`.
+    async fn decode<T: serde::de::DeserializeOwned>(resp: reqwest::Response) -> ApiResult<T> {
+        let body = resp.text().await?;
+        serde_json::from_str(&body).map_err(|e| ApiError::Decode(e.to_string()))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: Url) -> ApiResult<T> {
+        let resp = Self::check(self.http.get(url).send().await?).await?;
+        Self::decode(resp).await
+    }
+
+    async fn post_empty(&self, url: Url) -> ApiResult<reqwest::Response> {
+        let resp = self.http.post(url).json(&serde_json::json!({})).send().await?;
+        Self::check(resp).await
+    }
+
+    pub async fn get_tandc(&self, version: Option<&str>) -> ApiResult<TandCResponse> {
         let url = match version {
-            Some(v) => self.base.join(&format!("/TandC/{v}"))?,
-            None => self.base.join("/TandC")?,
+            Some(v) => self.url(&format!("/TandC/{v}"))?,
+            None => self.url("/TandC")?,
         };
-        let resp = self.http.get(url).send().await?.error_for_status()?;
-        Ok(resp.json().await?)
+        self.retrying("TandC", || self.get_json(url.clone())).await
     }
 
-    pub async fn register(&self, address: &str, signature_hex: &str, pubkey_hex: &str) -> anyhow::Result<RegistrationReceipt> {
-        let url = self.base.join(&format!("/register/{}/{}/{}", address, signature_hex, pubkey_hex))?;
-        let resp = self.http.post(url).json(&serde_json::json!({})).send().await?;
-        let resp = resp.error_for_status().context("register failed")?;
-        Ok(resp.json().await?)
+    /// Registering the same address again is accepted by the server, so a
+    /// retry after an in-flight failure is safe.
+    pub async fn register(&self, address: &str, signature_hex: &str, pubkey_hex: &str) -> ApiResult<RegistrationReceipt> {
+        let url = self.url(&format!("/register/{}/{}/{}", address, signature_hex, pubkey_hex))?;
+        self.retrying("register", || async {
+            Self::decode(self.post_empty(url.clone()).await?).await
+        })
+        .await
     }
 
-    pub async fn get_challenge(&self) -> anyhow::Result<ChallengeEnvelope> {
-        let url = self.base.join("/challenge")?;
-        let resp = self.http.get(url).send().await?.error_for_status()?;
-        Ok(resp.json().await?)
+    pub async fn get_challenge(&self) -> ApiResult<ChallengeEnvelope> {
+        let url = self.url("/challenge")?;
+        self.retrying("challenge", || self.get_json(url.clone())).await
     }
 
+    async fn submit_once(&self, url: Url) -> ApiResult<CryptoReceiptEnvelope> {
+        Self::decode(self.post_empty(url).await?).await
+    }
+
+    /// Submit a nonce. A solution can only be accepted once per address, so
+    /// if an attempt failed after the request may have reached the server and
+    /// a retry then reports `AlreadySolved`, this returns
+    /// `AcceptedWithoutReceipt` instead. So does a success response whose
+    /// receipt cannot be parsed: the server took the solution regardless.
     pub async fn submit_solution(
         &self,
         address: &str,
         challenge_id: &str,
         nonce_hex: &str,
-    ) -> anyhow::Result<CryptoReceiptEnvelope> {
-        let url = self
-            .base
-            .join(&format!("/solution/{}/{}/{}", address, challenge_id, nonce_hex))?;
-        let resp = self.http.post(url).json(&serde_json::json!({})).send().await?;
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            anyhow::bail!("submit failed: {} – {}", status, body);
+    ) -> ApiResult<CryptoReceiptEnvelope> {
+        let url = self.url(&format!("/solution/{}/{}/{}", address, challenge_id, nonce_hex))?;
+        let maybe_processed = AtomicBool::new(false);
+
+        let result = self
+            .retrying("solution", || async {
+                let r = self.submit_once(url.clone()).await;
+                if let Err(e) = &r {
+                    if may_have_been_processed(e) {
+                        maybe_processed.store(true, Ordering::Relaxed);
+                    }
+                }
+                r
+            })
+            .await;
+
+        match result {
+            Err(ApiError::AlreadySolved) if maybe_processed.load(Ordering::Relaxed) => {
+                Err(ApiError::AcceptedWithoutReceipt)
+            }
+            Err(ApiError::Decode(e)) => {
+                tracing::warn!("Unreadable receipt for {} on {}: {e}", address, challenge_id);
+                Err(ApiError::AcceptedWithoutReceipt)
+            }
+            other => other,
         }
-        Ok(resp.json().await?)
     }
 
     /// Fetch STAR-per-receipt array for each day.
     /// Endpoint: GET /work_to_star_rate
     /// - Example: [10882519, 7692307, 12487254]
     /// - 1 NIGHT = 1_000_000 STAR
-    pub async fn get_work_to_star_rate(&self) -> anyhow::Result<Vec<u64>> {
-        let url = self.base.join("/work_to_star_rate")?;
-        let resp = self.http.get(url).send().await?.error_for_status()?;
+    pub async fn get_work_to_star_rate(&self) -> ApiResult<Vec<u64>> {
+        let url = self.url("/work_to_star_rate")?;
         // Server returns a plain JSON array of integers
-        let v: Vec<u64> = resp.json().await?;
-        Ok(v)
+        self.retrying("work_to_star_rate", || self.get_json(url.clone())).await
     }
 
     /// Works 100% with current backend:
     /// Try to submit an intentionally invalid nonce.
-    /// If solution already exists → server returns "Solution already exists".
-    pub async fn probe_solution(&self, address: &str, challenge_id: &str) -> anyhow::Result<bool> {
+    /// If solution already exists → server answers `AlreadySolved`,
+    /// any other rejection of the nonce means not solved yet.
+    pub async fn probe_solution(&self, address: &str, challenge_id: &str) -> ApiResult<bool> {
         let fake_nonce = "0000000000000000";
-
-        let url = self
-            .base
-            .join(&format!("/solution/{}/{}/{}", address, challenge_id, fake_nonce))?;
-
-        let resp = self.http.post(url).json(&serde_json::json!({})).send().await?;
-
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-
-        if status == 400 && body.contains("Solution already exists") {
-            return Ok(true);
+        let url = self.url(&format!("/solution/{}/{}/{}", address, challenge_id, fake_nonce))?;
+
+        match self.retrying("probe", || self.submit_once(url.clone())).await {
+            Err(ApiError::AlreadySolved) => Ok(true),
+            Err(ApiError::InvalidNonce(_)) | Err(ApiError::NotRegistered(_)) => Ok(false),
+            Err(ApiError::Rejected { status, .. }) if status == reqwest::StatusCode::BAD_REQUEST => {
+                Ok(false)
+            }
+            Ok(_) => Ok(false), // should never happen for fake nonce
+            Err(e) => Err(e),
         }
-
-        if status == 400 {
-            return Ok(false); // "invalid nonce" → means not solved yet
-        }
-
-        if status.is_success() {
-            return Ok(false); // should never happen for fake nonce
-        }
-
-        Err(anyhow::anyhow!(
-            "unexpected solution probe response {} body={}",
-            status,
-            body
-        ))
     }
 
     pub async fn donate_to(
@@ -113,19 +185,28 @@ impl ScavengerClient {
         dest_addr: &str,
         src_addr: &str,
         sig_hex: &str,
-    ) -> anyhow::Result<String> {
-        let url = self.base.join(&format!(
+    ) -> ApiResult<String> {
+        let url = self.url(&format!(
             "/donate_to/{}/{}/{}",
             dest_addr, src_addr, sig_hex
         ))?;
 
-        let resp = self.http
-            .post(url)
-            .json(&serde_json::json!({}))
-            .send()
-            .await?
-            .error_for_status()?;
+        self.retrying("donate_to", || async {
+            Ok(self.post_empty(url.clone()).await?.text().await?)
+        })
+        .await
+    }
+}
 
-        Ok(resp.text().await?)
+/// Whether a failed call may still have been processed by the server
+/// (the request was sent but the answer got lost or timed out).
+fn may_have_been_processed(e: &ApiError) -> bool {
+    match e {
+        ApiError::Transport(t) => !t.is_connect(),
+        ApiError::ServerError { status, .. } => {
+            *status == reqwest::StatusCode::BAD_GATEWAY
+                || *status == reqwest::StatusCode::GATEWAY_TIMEOUT
+        }
+        _ => false,
     }
-}
\ No newline at end of file
+}
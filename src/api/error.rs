@@ -0,0 +1,216 @@
+use reqwest::StatusCode;
+use std::time::Duration;
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Classified failure of a Scavenger API call.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    /// The address already has a solution for this challenge.
+    #[error("solution already exists")]
+    AlreadySolved,
+
+    /// The nonce does not satisfy the challenge.
+    #[error("invalid nonce: {0}")]
+    InvalidNonce(String),
+
+    /// The address has not been registered (or is unknown to the server).
+    #[error("address not registered: {0}")]
+    NotRegistered(String),
+
+    #[error("rate limited (retry after {retry_after:?})")]
+    RateLimited { retry_after: Option<Duration> },
+
+    /// The challenge is no longer accepting submissions.
+    #[error("challenge expired: {0}")]
+    ChallengeExpired(String),
+
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("server error {status}: {message}")]
+    ServerError {
+        status: StatusCode,
+        message: String,
+        retry_after: Option<Duration>,
+    },
+
+    /// Any other non-success response.
+    #[error("request rejected {status}: {message}")]
+    Rejected { status: StatusCode, message: String },
+
+    /// A retried `/solution` came back `AlreadySolved` after an earlier
+    /// attempt failed in flight: the first attempt was most likely accepted,
+    /// but its crypto receipt never reached us.
+    #[error("solution accepted by an earlier attempt, receipt lost")]
+    AcceptedWithoutReceipt,
+
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    /// A success response whose body is not what the endpoint returns.
+    /// Not retried: asking again gets the same answer.
+    #[error("malformed response: {0}")]
+    Decode(String),
+
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
+}
+
+/// `Retry-After` as delay-seconds or an HTTP-date (a date in the past means
+/// retry now). Anything else is ignored and the normal backoff applies.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+impl ApiError {
+    /// Build an error from a non-success response, consuming its body.
+    pub async fn from_response(resp: reqwest::Response) -> Self {
+        let status = resp.status();
+        let retry_after = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = resp.text().await.unwrap_or_default();
+        Self::classify(status, retry_after, &body)
+    }
+
+    /// HTTP status behind this error, if the server answered at all.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ApiError::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            ApiError::Forbidden(_) => Some(StatusCode::FORBIDDEN),
+            ApiError::ServerError { status, .. } | ApiError::Rejected { status, .. } => {
+                Some(*status)
+            }
+            ApiError::Transport(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// Server supplied `Retry-After`, if any.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RateLimited { retry_after } => *retry_after,
+            ApiError::ServerError { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Classify by status code; the server's error message only decides
+    /// between the 4xx codes it uses for several failures (mostly 400).
+    pub fn classify(status: StatusCode, retry_after: Option<Duration>, body: &str) -> Self {
+        let message = error_message(body);
+
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited { retry_after },
+            StatusCode::CONFLICT => ApiError::AlreadySolved,
+            StatusCode::GONE => ApiError::ChallengeExpired(message),
+            s if s.is_server_error() => ApiError::ServerError { status, message, retry_after },
+            s if s.is_client_error() => Self::classify_message(status, message),
+            _ => ApiError::Rejected { status, message },
+        }
+    }
+
+    /// Fallback for 4xx responses: match the server's error message.
+    fn classify_message(status: StatusCode, message: String) -> Self {
+        let lower = message.to_lowercase();
+
+        if lower.contains("already exists") || lower.contains("already solved") {
+            ApiError::AlreadySolved
+        } else if lower.contains("not registered") || lower.contains("unregistered") {
+            ApiError::NotRegistered(message)
+        } else if lower.contains("expired")
+            || lower.contains("no longer")
+            || lower.contains("submission window")
+        {
+            ApiError::ChallengeExpired(message)
+        } else if lower.contains("nonce")
+            || lower.contains("difficulty")
+            || lower.contains("invalid solution")
+        {
+            ApiError::InvalidNonce(message)
+        } else if status == StatusCode::FORBIDDEN {
+            ApiError::Forbidden(message)
+        } else {
+            ApiError::Rejected { status, message }
+        }
+    }
+}
+
+/// Extract the human readable message from a JSON error body such as
+/// `{"message": "...", "error": "Bad Request", "statusCode": 400}`.
+/// Falls back to the raw body when it is not JSON.
+fn error_message(body: &str) -> String {
+    let Ok(v) = serde_json::from_str::<serde_json::Value>(body) else {
+        return body.trim().to_string();
+    };
+
+    let from = |key: &str| match v.get(key) {
+        Some(serde_json::Value::String(s)) => Some(s.clone()),
+        Some(serde_json::Value::Array(items)) => Some(
+            items
+                .iter()
+                .filter_map(|i| i.as_str())
+                .collect::<Vec<_>>()
+                .join("; "),
+        ),
+        _ => None,
+    };
+
+    from("message")
+        .or_else(|| from("error"))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(status: u16, body: &str) -> ApiError {
+        ApiError::classify(StatusCode::from_u16(status).unwrap(), None, body)
+    }
+
+    #[test]
+    fn status_decides_before_message() {
+        // A 5xx mentioning a nonce is still a (retryable) server error
+        assert!(matches!(classify(502, "invalid nonce"), ApiError::ServerError { .. }));
+        assert!(matches!(classify(429, "Solution already exists"), ApiError::RateLimited { .. }));
+        assert!(matches!(classify(409, "conflict"), ApiError::AlreadySolved));
+        assert!(matches!(classify(410, "gone"), ApiError::ChallengeExpired(_)));
+    }
+
+    #[test]
+    fn message_classifies_4xx() {
+        let body = r#"{"message":"Solution already exists","error":"Bad Request","statusCode":400}"#;
+        assert!(matches!(classify(400, body), ApiError::AlreadySolved));
+        assert!(matches!(classify(400, "Address is not registered"), ApiError::NotRegistered(_)));
+        assert!(matches!(classify(400, "Challenge expired"), ApiError::ChallengeExpired(_)));
+        assert!(matches!(classify(400, "Invalid nonce"), ApiError::InvalidNonce(_)));
+        assert!(matches!(classify(403, "Source address already donated"), ApiError::Forbidden(_)));
+        assert!(matches!(classify(404, "Challenge not found"), ApiError::Rejected { .. }));
+    }
+
+    #[test]
+    fn non_error_status_is_not_matched_by_message() {
+        assert!(matches!(classify(302, "already exists"), ApiError::Rejected { .. }));
+    }
+
+    #[test]
+    fn retry_after_takes_seconds_or_http_date() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+
+        let soon = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let delay = parse_retry_after(&soon).unwrap();
+        assert!(delay > Duration::from_secs(80) && delay <= Duration::from_secs(90), "{delay:?}");
+
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
@@ -1,5 +1,9 @@
 pub mod types;
 pub mod client;
+pub mod error;
+pub mod retry;
 
 pub use client::ScavengerClient;
+pub use error::ApiError;
+pub use retry::RetryPolicy;
 pub use types::*;
\ No newline at end of file
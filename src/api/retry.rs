@@ -0,0 +1,103 @@
+use rand::Rng;
+use std::time::Duration;
+
+use super::error::ApiError;
+
+/// How failed API calls are retried.
+///
+/// Delays grow as `base_delay * 2^(attempt - 1)` up to `max_delay`, minus a
+/// random `jitter` fraction so many miners don't retry in lockstep. A
+/// `Retry-After` sent by the server takes precedence, also capped at
+/// `max_delay`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one (1 disables retries).
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction (0.0..=1.0) of each delay that is randomised away.
+    pub jitter: f64,
+    /// Response status codes worth retrying.
+    pub retry_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            retry_statuses: vec![408, 425, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether `err` is transient and the call may be attempted again.
+    pub fn is_retryable(&self, err: &ApiError) -> bool {
+        match err {
+            ApiError::Transport(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            _ => err
+                .status()
+                .map(|s| self.retry_statuses.contains(&s.as_u16()))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Delay before attempt number `attempt + 1` (attempts count from 1).
+    pub fn delay(&self, attempt: u32, err: &ApiError) -> Duration {
+        if let Some(ra) = err.retry_after() {
+            return ra.min(self.max_delay);
+        }
+
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return exp;
+        }
+        let keep = 1.0 - jitter * rand::thread_rng().gen_range(0.0..1.0);
+        exp.mul_f64(keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn rate_limited(secs: u64) -> ApiError {
+        ApiError::RateLimited { retry_after: Some(Duration::from_secs(secs)) }
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        let p = policy(0.5);
+        assert_eq!(p.delay(1, &rate_limited(3)), Duration::from_secs(3));
+        assert_eq!(p.delay(1, &rate_limited(86_400)), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_delay() {
+        let p = policy(0.0);
+        let err = ApiError::RateLimited { retry_after: None };
+        let delays: Vec<u64> = (1..=8).map(|a| p.delay(a, &err).as_millis() as u64).collect();
+        assert_eq!(delays, [500, 1_000, 2_000, 4_000, 8_000, 16_000, 30_000, 30_000]);
+
+        // Jitter only ever shortens the delay
+        let d = policy(0.5).delay(3, &err);
+        assert!(d >= Duration::from_secs(1) && d <= Duration::from_secs(2), "{d:?}");
+    }
+}
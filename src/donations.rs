@@ -1,11 +1,10 @@
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
-/// Single donation event written to JSONL log.
+use crate::store::Store;
+
+/// Single donation event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DonationRecord {
     /// Donor (source) address that consolidates its claims away.
@@ -16,10 +15,9 @@ pub struct DonationRecord {
     pub timestamp: String,
 }
 
-/// JSONL manager for donations:
-///   keystore/00donations.jsonl
+/// Donations we made, kept in the SQLite store (see `Store`).
 pub struct Donations {
-    path: PathBuf,
+    store: Store,
 }
 
 impl Donations {
@@ -31,56 +29,17 @@ impl Donations {
 
     /// Construct from explicit keystore directory.
     pub fn new<P: AsRef<Path>>(keystore_dir: P) -> Result<Self> {
-        let root = keystore_dir.as_ref();
-        fs::create_dir_all(root)?;
-        let path = root.join("00donations.jsonl");
-        Ok(Self { path })
+        Ok(Self { store: Store::new(keystore_dir)? })
     }
 
-    /// Append one donation record (as JSON per line).
+    /// Record one donation.
     pub fn append_donation(&self, rec: &DonationRecord) -> Result<()> {
-        let mut f = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.path)?;
-        let line = serde_json::to_string(rec)?;
-        writeln!(f, "{}", line)?;
-        Ok(())
+        self.store.insert_donation(rec)
     }
 
     /// Read all donation records.
     pub fn read_all(&self) -> Result<Vec<DonationRecord>> {
-        if !self.path.exists() {
-            return Ok(Vec::new());
-        }
-        let f = OpenOptions::new().read(true).open(&self.path)?;
-        let reader = BufReader::new(f);
-        let mut out = Vec::new();
-        for line in reader.lines() {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue;
-            }
-            match serde_json::from_str::<DonationRecord>(&line) {
-                Ok(rec) => out.push(rec),
-                Err(e) => {
-                    // tolerate a bad line; continue
-                    tracing::warn!("Ignoring malformed donation line: {e}");
-                }
-            }
-        }
-        Ok(out)
-    }
-
-    /// Quick helpers built from the log.
-    fn build_sets(&self) -> Result<(HashSet<String>, HashSet<String>)> {
-        let mut sources = HashSet::new();
-        let mut targets = HashSet::new();
-        for r in self.read_all()? {
-            sources.insert(r.source);
-            targets.insert(r.target);
-        }
-        Ok((sources, targets))
+        self.store.donations()
     }
 
     /// Check if `source -> target` is allowed.
@@ -104,21 +63,19 @@ impl Donations {
             bail!("source address has no receipts yet");
         }
 
-        let (sources, targets) = self.build_sets()?;
-
         // Rule 1: source can donate only once
-        if sources.contains(source) {
+        if self.store.has_donated(source)? {
             bail!("source {} already donated", source);
         }
 
         // Rule 2: no chains (target must not be a source anywhere)
-        if sources.contains(target) {
+        if self.store.has_donated(target)? {
             bail!("target {} has acted as a source before (donation chain not allowed)", target);
         }
 
         // Also: source must not appear as a target already (if it already received a donation,
         // the server will forbid it donating onwards)
-        if targets.contains(source) {
+        if self.store.has_received(source)? {
             bail!("source {} already received a donation (cannot donate further)", source);
         }
 
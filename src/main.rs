@@ -1,9 +1,15 @@
 mod api;
 mod address;
 mod mining;
+mod mock;
+mod shutdown;
 mod util;
 pub mod accounting;
 pub mod donations;
+pub mod outbox;
+pub mod projection;
+pub mod stats;
+pub mod store;
 
 use clap::{Parser, Subcommand, ValueEnum};
 use tracing_subscriber::EnvFilter;
@@ -31,6 +37,26 @@ struct Cli {
     #[arg(long, env = "WORKERS")]
     workers: Option<usize>,
 
+    /// Number of addresses to mine concurrently per challenge (workers are split between them)
+    #[arg(long, env = "PARALLEL_ADDRESSES", default_value_t = 1)]
+    parallel_addresses: usize,
+
+    /// Attempts per API call before giving up (1 disables retries)
+    #[arg(long, env = "API_RETRIES", default_value_t = 5)]
+    api_retries: u32,
+
+    /// Initial retry delay in milliseconds (doubles per attempt)
+    #[arg(long, env = "API_RETRY_BASE_MS", default_value_t = 500)]
+    api_retry_base_ms: u64,
+
+    /// Upper bound for a single retry delay in milliseconds
+    #[arg(long, env = "API_RETRY_MAX_MS", default_value_t = 30_000)]
+    api_retry_max_ms: u64,
+
+    /// Seconds between /challenge polls while mining (new challenges preempt the running one)
+    #[arg(long, env = "CHALLENGE_POLL_SECS", default_value_t = 30)]
+    challenge_poll_secs: u64,
+
     /// Log level (error|warn|info|debug|trace)
     #[arg(long, env = "RUST_LOG", default_value = "info")]
     log: String,
@@ -39,6 +65,23 @@ struct Cli {
     #[arg(long, env = "KEYSTORE", default_value = "keystore")]
     keystore: String,
 
+    /// Type of newly generated addresses
+    #[arg(long, env = "ADDRESS_TYPE", value_enum, default_value_t = address::AddressType::Enterprise)]
+    address_type: address::AddressType,
+
+    /// Stake key shared by new base addresses: key hash or stake vkey (hex), stake_vk1… or stake address.
+    /// Without it every base address gets its own stake key
+    #[arg(long, env = "STAKE_KEY")]
+    stake_key: Option<String>,
+
+    /// File holding the keystore passphrase (otherwise KEYSTORE_PASSPHRASE or a prompt)
+    #[arg(long, env = "KEYSTORE_PASSPHRASE_FILE")]
+    passphrase_file: Option<std::path::PathBuf>,
+
+    /// Start even if an encrypted keystore still contains plaintext keys
+    #[arg(long, env = "ALLOW_MIXED_KEYSTORE", default_value_t = false)]
+    allow_mixed_keystore: bool,
+
     /// Enable donate_to calls after registering address (optional)
     #[arg(long, env = "ENABLE_DONATE", default_value_t = false)]
     enable_donate: bool,
@@ -47,6 +90,14 @@ struct Cli {
     #[arg(long, env = "DONATE_TO", default_value = "")]
     donate_to: String,
 
+    /// Public key format sent to /register: raw 32-byte key or a CIP-30 COSE_Key
+    #[arg(long, env = "REGISTER_KEY", value_enum, default_value_t = mining::RegisterKey::Raw)]
+    register_key: mining::RegisterKey,
+
+    /// Sign the blake2b-224 hash of register/donate messages instead of the messages (CIP-8 hashed mode)
+    #[arg(long, env = "CIP8_HASHED", default_value_t = false)]
+    cip8_hashed: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -57,58 +108,592 @@ enum Commands {
     Mine,
     /// Just fetch the current challenge and print it
     Challenge,
-    /// Generate a real Shelley enterprise address and print it
+    /// Generate a real Shelley address (see --address-type) and print it
     GenAddr,
+    /// Encrypt the plaintext keys in the keystore with a passphrase (Argon2id + XChaCha20-Poly1305)
+    EncryptKeystore,
+    /// Rebuild the keystore index (00index.json) that fixes the address rotation order
+    RepairIndex,
+    /// Switch the keystore to HD keys (CIP-1852) derived from a BIP-39 mnemonic
+    HdInit {
+        /// Existing mnemonic to restore from (a new 24-word one is generated if omitted)
+        #[arg(long, env = "MNEMONIC", hide_env_values = true)]
+        mnemonic: Option<String>,
+        /// Optional BIP-39 passphrase
+        #[arg(long, env = "MNEMONIC_PASSPHRASE", default_value = "", hide_env_values = true)]
+        passphrase: String,
+        /// Number of addresses already in use (when restoring)
+        #[arg(long, default_value_t = 0)]
+        used: u32,
+    },
+    /// Export keys as cardano-cli key files (<out>/<address>/payment.skey, …) or bech32 strings
+    Export {
+        /// Directory to write the key files to
+        #[arg(long, default_value = "export")]
+        out: std::path::PathBuf,
+        /// Only export this address
+        #[arg(long)]
+        address: Option<String>,
+        /// Print `<address> <ed25519_sk…> [<stake key>]` lines instead of writing files
+        #[arg(long, default_value_t = false)]
+        bech32: bool,
+    },
+    /// Import a signing key into the keystore (address type from --address-type / --stake-key)
+    Import {
+        /// Payment signing key: cardano-cli .skey file or bech32 ed25519_sk1… / ed25519e_sk1…
+        key: String,
+        /// Stake signing key (.skey or bech32) for a base address with its own stake key
+        #[arg(long)]
+        stake_signing_key: Option<String>,
+    },
+    /// Sign a message with a keystore address like CIP-30 signData and print {signature, key}
+    SignData {
+        /// Keystore address to sign with
+        address: String,
+        /// Message to sign (UTF-8)
+        message: String,
+        /// Sign the blake2b-224 hash of the message instead of the message (CIP-8 hashed mode)
+        #[arg(long, default_value_t = false)]
+        hashed: bool,
+    },
+    /// Decode a COSE_Sign1 signature (hex or a file holding it) and verify it
+    VerifySig {
+        /// COSE_Sign1 as hex, a CIP-30 {signature, key} JSON, or a file with either (or raw CBOR)
+        signature: String,
+        /// Public key: hex, .vkey file or bech32 (default: the COSE_Key or the `kid` header)
+        #[arg(long)]
+        pubkey: Option<String>,
+        /// COSE_Key (hex) holding the public key, as returned by CIP-30 signData
+        #[arg(long)]
+        key: Option<String>,
+        /// Expected payload (e.g. the T&C message)
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// Write receipts, registrations, donations, challenges and STAR rates from the accounting database as JSONL
+    ExportAccounting {
+        /// Directory to write 00receipts.jsonl, 00registrations.jsonl, 00donations.jsonl, 00challenges.jsonl and 00star_rates.json to
+        #[arg(long, default_value = "accounting-export")]
+        out: std::path::PathBuf,
+    },
+    /// Solutions, STAR and NIGHT per day, per address and per challenge (offline)
+    Stats {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = stats::StatsFormat::Table)]
+        format: stats::StatsFormat,
+        /// Only this breakdown (default: all)
+        #[arg(long, value_enum)]
+        by: Option<stats::StatsBy>,
+        /// Only receipts from this UTC date on (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+        /// Only receipts up to and including this UTC date (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<chrono::NaiveDate>,
+    },
+    /// Project NIGHT by the end of the mining period from STAR rates, hashrate and difficulty trend
+    Project {
+        /// Hashrate to project with in H/s (e.g. from `bench`); default: measured from recent receipts
+        #[arg(long)]
+        hashrate: Option<f64>,
+        /// Current day (default: from the server)
+        #[arg(long)]
+        current_day: Option<u32>,
+        /// Last day of the mining period (default: from the server)
+        #[arg(long)]
+        max_day: Option<u32>,
+        /// Print the projection as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Check stored server receipts: preimage, server signature and AshMaize difficulty (offline)
+    VerifyReceipts {
+        /// Server public key the receipts are signed with (hex, .vkey file or bech32)
+        #[arg(long, env = "SCAVENGER_SERVER_PUBKEY")]
+        server_pubkey: Option<String>,
+        /// Skip re-computing the AshMaize hashes (avoids building a ROM per day)
+        #[arg(long, default_value_t = false)]
+        no_hash: bool,
+        /// Print the results as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Measure AshMaize hashrate on this machine (offline)
+    Bench {
+        /// Highest thread count to measure (defaults to all CPU cores)
+        #[arg(long)]
+        threads: Option<usize>,
+        /// Seconds to hash at each thread count
+        #[arg(long, default_value_t = 10)]
+        seconds: u64,
+        /// Print the results as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Run a local mock of the Scavenger API (for dry runs with --api http://<listen>)
+    MockServer {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Difficulty mask (8 hex chars, more zero bits = harder)
+        #[arg(long, default_value = "0FFFFFFF")]
+        difficulty: String,
+        /// Seconds between new challenges
+        #[arg(long, default_value_t = 600)]
+        rotate_secs: u64,
+        /// Seconds each challenge accepts submissions
+        #[arg(long, default_value_t = 86_400)]
+        window_secs: u64,
+        /// Challenges per mock day (no_pre_mine changes daily)
+        #[arg(long, default_value_t = 24)]
+        challenges_per_day: u32,
+        /// Days in the mock mining period (`max_day`)
+        #[arg(long, default_value_t = 21)]
+        days: u32,
+        /// STAR per receipt reported for every day
+        #[arg(long, default_value_t = 10_000_000)]
+        star_rate: u64,
+        /// Fraction of requests answered with an injected 429/5xx (0.0-1.0)
+        #[arg(long, default_value_t = 0.0)]
+        error_rate: f64,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     let log = cli.log.clone(); // avoid moving cli
+    // Logs go to stderr so stdout only carries command output (e.g. --json)
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::new(log))
+        .with_writer(std::io::stderr)
         .init();
 
     match cli.command {
         Commands::Mine => cmd_mine(cli).await?,
         Commands::Challenge => cmd_challenge(cli).await?,
         Commands::GenAddr => cmd_gen_addr(cli).await?,
+        Commands::RepairIndex => {
+            let r = address::index::KeyIndex::new(&cli.keystore).repair()?;
+            println!(
+                "Index rebuilt: {} kept, {} added (by mtime), {} dropped",
+                r.kept, r.added, r.dropped
+            );
+        }
+        Commands::EncryptKeystore => {
+            let n = address::sealed::encrypt_keystore(&cli.keystore, cli.passphrase_file.as_deref())?;
+            println!("Encrypted {} key files in {}", n, cli.keystore);
+        }
+        Commands::HdInit { ref mnemonic, ref passphrase, used } => {
+            cmd_hd_init(&cli, mnemonic.clone(), passphrase, used).await?
+        }
+        Commands::Export { ref out, ref address, bech32 } => {
+            cmd_export(&cli, out, address.as_deref(), bech32).await?
+        }
+        Commands::Import { ref key, ref stake_signing_key } => {
+            cmd_import(&cli, key, stake_signing_key.as_deref()).await?
+        }
+        Commands::SignData { ref address, ref message, hashed } => {
+            cmd_sign_data(&cli, address, message, hashed).await?
+        }
+        Commands::VerifySig { ref signature, ref pubkey, ref key, ref message } => {
+            cmd_verify_sig(signature, pubkey.as_deref(), key.as_deref(), message.as_deref())?
+        }
+        Commands::ExportAccounting { ref out } => {
+            let n = accounting::Accounting::new(&cli.keystore)?.export_jsonl(out)?;
+            println!(
+                "Exported {} receipts, {} registrations, {} donations, {} challenges and {} STAR rates to {}",
+                n.receipts,
+                n.registrations,
+                n.donations,
+                n.challenges,
+                n.star_rates,
+                out.display()
+            );
+        }
+        Commands::Stats { format, by, since, until } => {
+            let accounting = accounting::Accounting::new(&cli.keystore)?;
+            let donations = donations::Donations::new(&cli.keystore)?;
+            let stats = stats::collect(
+                &accounting.receipts_between(since, until)?,
+                &accounting.read_star_rates()?,
+                &donations.read_all()?,
+                &accounting.challenges()?,
+            );
+            stats.print(format, by)?;
+        }
+        Commands::Project { hashrate, current_day, max_day, json } => {
+            cmd_project(&cli, hashrate, current_day, max_day, json).await?
+        }
+        Commands::VerifyReceipts { ref server_pubkey, no_hash, json } => {
+            cmd_verify_receipts(&cli, server_pubkey.as_deref(), no_hash, json).await?
+        }
+        Commands::Bench { threads, seconds, json } => cmd_bench(threads, seconds, json).await?,
+        Commands::MockServer {
+            listen,
+            difficulty,
+            rotate_secs,
+            window_secs,
+            challenges_per_day,
+            days,
+            star_rate,
+            error_rate,
+        } => {
+            let cfg = mock::MockConfig {
+                difficulty,
+                rotate_secs,
+                window_secs,
+                challenges_per_day,
+                days,
+                star_rate,
+                error_rate,
+            };
+            mock::serve(&listen, cfg).await?
+        }
     }
 
     Ok(())
 }
 
+impl Cli {
+    /// Stake credential for new addresses (`None`: enterprise addresses).
+    fn stake_credential(&self) -> anyhow::Result<Option<address::StakeCredential>> {
+        use address::{AddressType, StakeCredential};
+        match (self.address_type, &self.stake_key) {
+            (AddressType::Enterprise, None) => Ok(None),
+            (AddressType::Enterprise, Some(_)) => anyhow::bail!("--stake-key requires --address-type base"),
+            (AddressType::Base, None) => Ok(Some(StakeCredential::PerAddress)),
+            (AddressType::Base, Some(key)) => {
+                let hash = address::stake::parse_stake_key_hash(key, self.network.network_id())?;
+                Ok(Some(StakeCredential::shared(hash)))
+            }
+        }
+    }
+
+    /// Cipher for an encrypted keystore (asks for the passphrase if needed).
+    fn unlock_keystore(&self) -> anyhow::Result<Option<std::sync::Arc<address::sealed::KeystoreCipher>>> {
+        address::sealed::unlock(&self.keystore, self.passphrase_file.as_deref(), self.allow_mixed_keystore)
+    }
+
+    fn client(&self) -> anyhow::Result<api::ScavengerClient> {
+        let retry = api::RetryPolicy {
+            max_attempts: self.api_retries.max(1),
+            base_delay: std::time::Duration::from_millis(self.api_retry_base_ms),
+            max_delay: std::time::Duration::from_millis(self.api_retry_max_ms),
+            ..Default::default()
+        };
+        Ok(api::ScavengerClient::new(self.api.clone())?.with_retry(retry))
+    }
+}
+
 async fn cmd_challenge(cli: Cli) -> anyhow::Result<()> {
-    let client = api::ScavengerClient::new(cli.api)?;
+    let client = cli.client()?;
     let ch = client.get_challenge().await?;
     println!("{}", serde_json::to_string_pretty(&ch)?);
     Ok(())
 }
 
 async fn cmd_gen_addr(cli: Cli) -> anyhow::Result<()> {
-    let ap = address::shelley::ShelleyProvider::new(cli.network, &cli.keystore).await?;
+    let cipher = cli.unlock_keystore()?;
+    let ap = address::shelley::ShelleyProvider::new(cli.network, &cli.keystore, cipher)
+        .await?
+        .with_stake(cli.stake_credential()?);
     let a = ap.new_address()?;
     println!("address: {}\npubkey_hex: {}", a.address, hex::encode(a.pubkey));
+    if let Some(skh) = a.stake_key_hash {
+        println!("stake_key_hash: {}", hex::encode(skh));
+    }
+    Ok(())
+}
+
+async fn cmd_export(
+    cli: &Cli,
+    out: &std::path::Path,
+    address: Option<&str>,
+    bech32: bool,
+) -> anyhow::Result<()> {
+    use address::envelope::signing_bech32;
+
+    let cipher = cli.unlock_keystore()?;
+    let ap = address::shelley::ShelleyProvider::new(cli.network, &cli.keystore, cipher).await?;
+    let keys: Vec<_> = ap
+        .export_keys()?
+        .into_iter()
+        .filter(|k| address.is_none_or(|a| k.address == a))
+        .collect();
+    if keys.is_empty() {
+        anyhow::bail!("no matching keys in {}", cli.keystore);
+    }
+
+    for k in &keys {
+        if bech32 {
+            let stake = k.stake.as_ref().map(|s| signing_bech32(&s.key)).unwrap_or_default();
+            println!("{} {} {}", k.address, signing_bech32(&k.payment.key), stake);
+        } else {
+            k.write_dir(&out.join(&k.address))?;
+        }
+    }
+    if !bech32 {
+        println!("Exported {} addresses to {}", keys.len(), out.display());
+    }
+    Ok(())
+}
+
+async fn cmd_import(cli: &Cli, key: &str, stake_signing_key: Option<&str>) -> anyhow::Result<()> {
+    use address::envelope::parse_signing_key;
+
+    let privkey = parse_signing_key(key)?;
+    let stake_key = stake_signing_key.map(parse_signing_key).transpose()?;
+
+    let cipher = cli.unlock_keystore()?;
+    let ap = address::shelley::ShelleyProvider::new(cli.network, &cli.keystore, cipher)
+        .await?
+        .with_stake(cli.stake_credential()?);
+    let a = ap.import_key(privkey, stake_key)?;
+    println!("Imported {}", a.address);
+    Ok(())
+}
+
+async fn cmd_sign_data(cli: &Cli, address: &str, message: &str, hashed: bool) -> anyhow::Result<()> {
+    let cipher = cli.unlock_keystore()?;
+    let ap = address::shelley::ShelleyProvider::new(cli.network, &cli.keystore, cipher).await?;
+    let Some(k) = ap.export_keys()?.into_iter().find(|k| k.address == address) else {
+        anyhow::bail!("{} is not in {}", address, cli.keystore);
+    };
+
+    let raw = util::bech::bech32_decode_to_bytes(address);
+    let ds = util::cip8::sign_data(&k.payment.key, message.as_bytes(), &raw, hashed);
+    println!("{}", serde_json::to_string_pretty(&ds)?);
+    Ok(())
+}
+
+fn cmd_verify_sig(
+    signature: &str,
+    pubkey: Option<&str>,
+    cose_key: Option<&str>,
+    message: Option<&str>,
+) -> anyhow::Result<()> {
+    use util::bech::{bech32_encode, blake2b224};
+    use util::cip8::{decode_cose_key, decode_cose_sign1, diagnostic, DataSignature};
+
+    let path = std::path::Path::new(signature);
+    let data = if path.is_file() { std::fs::read(path)? } else { signature.as_bytes().to_vec() };
+    let text = String::from_utf8_lossy(&data);
+    let (cose, embedded_cose_key) = match serde_json::from_str::<DataSignature>(&text) {
+        Ok(ds) => (hex::decode(ds.signature.trim())?, Some(ds.key)),
+        Err(_) if path.is_file() => (hex::decode(text.trim()).unwrap_or_else(|_| data.clone()), None),
+        Err(_) => (hex::decode(text.trim())?, None),
+    };
+    let cose_key = cose_key.map(str::to_string).or(embedded_cose_key);
+    let sign1 = decode_cose_sign1(&cose)?;
+
+    println!("protected:    {}", diagnostic(&sign1.protected));
+    println!("unprotected:  {}", diagnostic(&sign1.unprotected));
+    match std::str::from_utf8(&sign1.payload) {
+        Ok(text) => println!("payload:      {:?} ({} bytes)", text, sign1.payload.len()),
+        Err(_) => println!("payload:      h'{}'", hex::encode(&sign1.payload)),
+    }
+    println!("signature:    {}", hex::encode(&sign1.signature));
+    println!("to sign:      {}", hex::encode(sign1.sig_structure()));
+
+    let (key, source) = match (pubkey, cose_key) {
+        (Some(pk), _) => (address::envelope::parse_verification_key(pk)?, "supplied"),
+        (None, Some(k)) => (decode_cose_key(&hex::decode(k.trim())?)?, "COSE_Key"),
+        (None, None) => match sign1.embedded_key() {
+            Some(pk) => (pk, "kid header"),
+            None => anyhow::bail!("no public key in the signature headers; pass --pubkey or --key"),
+        },
+    };
+    println!("public key:   {} ({source})", hex::encode(key));
+
+    let mut ok = true;
+    if let Some(raw) = sign1.address() {
+        let hrp = if raw.first().map(|h| h & 0x0f) == Some(1) { "addr" } else { "addr_test" };
+        let matches = raw.len() >= 29 && raw[1..29] == blake2b224(&key);
+        println!(
+            "address:      {} (payment key {})",
+            bech32_encode(hrp, raw),
+            if matches { "matches" } else { "DOES NOT match" }
+        );
+        ok &= matches;
+    }
+    if let Some(message) = message {
+        let matches = sign1.payload_matches(message.as_bytes());
+        println!("message:      {}", if matches { "matches payload" } else { "DOES NOT match payload" });
+        ok &= matches;
+    }
+
+    match sign1.verify(&key) {
+        Ok(()) => println!("verification: OK"),
+        Err(e) => {
+            println!("verification: FAILED ({e})");
+            ok = false;
+        }
+    }
+    if !ok {
+        anyhow::bail!("signature check failed");
+    }
+    Ok(())
+}
+
+async fn cmd_hd_init(
+    cli: &Cli,
+    mnemonic: Option<String>,
+    passphrase: &str,
+    used: u32,
+) -> anyhow::Result<()> {
+    use address::hd::HdWallet;
+
+    let cipher = cli.unlock_keystore()?;
+    let keystore = &cli.keystore;
+
+    let generated = mnemonic.is_none();
+    let phrase = mnemonic.unwrap_or_else(HdWallet::generate_mnemonic);
+    HdWallet::create(keystore, &phrase, passphrase, used, cli.stake_credential()?, cipher.clone())?;
+
+    if generated {
+        println!("Recovery phrase (write it down, it restores every derived address):\n\n  {phrase}\n");
+    }
+
+    let ap = address::shelley::ShelleyProvider::new(cli.network, keystore, cipher).await?;
+    for (i, a) in ap.all_addresses()?.iter().enumerate() {
+        println!("  {}. {}", i, a.address);
+    }
+    println!("HD keystore created: {}/{}", keystore, HdWallet::FILE);
+    Ok(())
+}
+
+async fn cmd_project(
+    cli: &Cli,
+    hashrate: Option<f64>,
+    current_day: Option<u32>,
+    max_day: Option<u32>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let accounting = accounting::Accounting::new(&cli.keystore)?;
+
+    // Fresh numbers from the server if it is reachable
+    let client = cli.client()?;
+    let env = match client.get_challenge().await {
+        Ok(env) => Some(env),
+        Err(e) => {
+            tracing::warn!("Cannot fetch the current challenge, using stored data: {e}");
+            None
+        }
+    };
+    match client.get_work_to_star_rate().await {
+        Ok(rates) => accounting.write_star_rates(&rates)?,
+        Err(e) => tracing::warn!("Cannot fetch STAR rates, using stored ones: {e}"),
+    }
+
+    let current_day = current_day
+        .or_else(|| env.as_ref()?.current_day)
+        .ok_or_else(|| anyhow::anyhow!("current day unknown; pass --current-day"))?;
+    let max_day = max_day
+        .or_else(|| env.as_ref()?.max_day)
+        .ok_or_else(|| anyhow::anyhow!("last day of the mining period unknown; pass --max-day"))?;
+    let current_difficulty = env
+        .as_ref()
+        .and_then(|e| e.challenge.as_ref())
+        .map(|c| c.difficulty.clone());
+
+    let receipts = accounting.read_all_receipts()?;
+    let star_rates = accounting.read_star_rates()?;
+    let challenges = accounting.challenges()?;
+    let p = projection::project(&projection::ProjectionInput {
+        receipts: &receipts,
+        star_rates: &star_rates,
+        challenges: &challenges,
+        current_day,
+        max_day,
+        current_difficulty: current_difficulty.as_deref(),
+        hashrate,
+        now: chrono::Utc::now(),
+    })?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&p)?);
+    } else {
+        p.print_table();
+    }
+    Ok(())
+}
+
+async fn cmd_verify_receipts(
+    cli: &Cli,
+    server_pubkey: Option<&str>,
+    no_hash: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    let server_key = server_pubkey
+        .map(|k| -> anyhow::Result<_> {
+            let bytes = address::envelope::parse_verification_key(k)?;
+            Ok(ed25519_dalek::VerifyingKey::from_bytes(&bytes)?)
+        })
+        .transpose()?;
+
+    let accounting = accounting::Accounting::new(&cli.keystore)?;
+    let receipts = accounting.read_all_receipts()?;
+    let challenges = accounting.challenges()?;
+    let report = tokio::task::spawn_blocking(move || {
+        mining::verify::verify_receipts(&receipts, &challenges, server_key.as_ref(), !no_hash)
+    })
+    .await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        report.print_table();
+    }
+    if report.failed > 0 {
+        anyhow::bail!("{} receipts failed verification", report.failed);
+    }
+    Ok(())
+}
+
+async fn cmd_bench(threads: Option<usize>, seconds: u64, json: bool) -> anyhow::Result<()> {
+    let threads = threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|x| x.get()).unwrap_or(1));
+    let report = tokio::task::spawn_blocking(move || mining::bench::run_bench(threads, seconds)).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        report.print_table();
+    }
     Ok(())
 }
 
 async fn cmd_mine(cli: Cli) -> anyhow::Result<()> {
     use mining::Miner;
 
-    let client = api::ScavengerClient::new(cli.api.clone())?;
+    let client = cli.client()?;
     let tandc = client.get_tandc(None).await?;
     tracing::info!(version=?tandc.version, "fetched T&C");
 
-    let shelley = address::shelley::ShelleyProvider::new(cli.network, &cli.keystore).await?;
-    let addr_provider = address::prefill::PrefillProvider::new(shelley, &cli.keystore)?;
+    let cipher = cli.unlock_keystore()?;
+    let shelley = address::shelley::ShelleyProvider::new(cli.network, &cli.keystore, cipher.clone())
+        .await?
+        .with_stake(cli.stake_credential()?);
+    let addr_provider = address::prefill::PrefillProvider::new(shelley, &cli.keystore, cipher.as_deref())?;
+
+    // First Ctrl-C / SIGTERM stops mining after in-flight work, second one exits
+    let shutdown = shutdown::Shutdown::new();
+    shutdown.listen_for_signals();
+
     let mut miner = Miner::new(
         client, 
         addr_provider, 
+        &cli.keystore,
         cli.workers, 
+        cli.parallel_addresses,
         cli.network,
         cli.enable_donate,
         if cli.donate_to.is_empty() { None } else { Some(cli.donate_to) }
-    );
+    )
+    .with_shutdown(shutdown)
+    .with_challenge_poll(std::time::Duration::from_secs(cli.challenge_poll_secs.max(1)))
+    .with_register_key(cli.register_key)
+    .with_cip8_hashed(cli.cip8_hashed);
 
     // Run miner with stats
     miner.run_loop(tandc).await
@@ -0,0 +1,124 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::rom::build_rom;
+use super::worker::ash_hash;
+
+/// Fixed ROM seed so results are comparable between machines.
+const BENCH_SEED: &str = "scavenger-miner-bench";
+
+#[derive(Debug, Serialize)]
+pub struct BenchRun {
+    pub threads: usize,
+    pub hashes: u64,
+    pub hashes_per_sec: f64,
+    pub per_thread: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub available_parallelism: usize,
+    pub rom_build_secs: f64,
+    /// Resident memory after building the ROM (Linux only)
+    pub rss_bytes: Option<u64>,
+    pub seconds_per_run: u64,
+    pub runs: Vec<BenchRun>,
+}
+
+/// Thread counts to measure: powers of two up to `max`, plus `max` itself.
+fn thread_steps(max: usize) -> Vec<usize> {
+    let mut steps: Vec<usize> = std::iter::successors(Some(1usize), |t| t.checked_mul(2))
+        .take_while(|t| *t < max)
+        .collect();
+    steps.push(max.max(1));
+    steps
+}
+
+/// Build the ROM once, then hash for `secs` seconds at each thread count.
+pub fn run_bench(max_threads: usize, secs: u64) -> BenchReport {
+    tracing::info!("Building ROM for benchmark…");
+    let started = Instant::now();
+    let rom = Arc::new(build_rom(BENCH_SEED));
+    let rom_build_secs = started.elapsed().as_secs_f64();
+    let rss_bytes = resident_memory();
+
+    let mut runs = Vec::new();
+    for threads in thread_steps(max_threads) {
+        tracing::info!("Hashing with {} threads for {}s…", threads, secs);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let hashes = Arc::new(AtomicU64::new(0));
+
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let rom = rom.clone();
+                let stop = stop.clone();
+                let hashes = hashes.clone();
+                std::thread::spawn(move || {
+                    let mut n: u64 = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        let preimage = format!("{:016x}{}{:04}", n, BENCH_SEED, t);
+                        std::hint::black_box(ash_hash(preimage.as_bytes(), &rom));
+                        n += 1;
+                    }
+                    hashes.fetch_add(n, Ordering::Relaxed);
+                })
+            })
+            .collect();
+
+        let t0 = Instant::now();
+        std::thread::sleep(Duration::from_secs(secs));
+        stop.store(true, Ordering::Relaxed);
+        for h in handles {
+            let _ = h.join();
+        }
+        let elapsed = t0.elapsed().as_secs_f64();
+
+        let hashes = hashes.load(Ordering::Relaxed);
+        let hashes_per_sec = hashes as f64 / elapsed;
+        runs.push(BenchRun {
+            threads,
+            hashes,
+            hashes_per_sec,
+            per_thread: hashes_per_sec / threads as f64,
+        });
+    }
+
+    BenchReport {
+        available_parallelism: std::thread::available_parallelism()
+            .map(|x| x.get())
+            .unwrap_or(1),
+        rom_build_secs,
+        rss_bytes,
+        seconds_per_run: secs,
+        runs,
+    }
+}
+
+impl BenchReport {
+    pub fn print_table(&self) {
+        println!("ROM build time: {:.2}s", self.rom_build_secs);
+        match self.rss_bytes {
+            Some(b) => println!("Resident memory: {:.1} MiB", b as f64 / (1024.0 * 1024.0)),
+            None => println!("Resident memory: n/a"),
+        }
+        println!("Available parallelism: {}", self.available_parallelism);
+        println!();
+        println!("{:>8} {:>12} {:>12} {:>12}", "threads", "hashes", "H/s", "H/s/thread");
+        for r in &self.runs {
+            println!(
+                "{:>8} {:>12} {:>12.1} {:>12.1}",
+                r.threads, r.hashes, r.hashes_per_sec, r.per_thread
+            );
+        }
+    }
+}
+
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
@@ -1,41 +1,88 @@
+pub mod bench;
+pub mod poller;
+pub mod rom;
+pub mod scheduler;
+pub mod telemetry;
+pub mod verify;
 pub mod worker;
 
 use crate::accounting::{Accounting, ReceiptRecord};
 use crate::donations::{Donations, DonationRecord};
-use crate::api::{ScavengerClient, TandCResponse};
+use crate::outbox::{FoundSolution, Outbox, SolutionStatus};
+use crate::shutdown::Shutdown;
+use crate::api::{ApiError, Challenge, ScavengerClient, TandCResponse};
 use crate::address::{AddressBundle, AddressProvider};
 use crate::Network;
 
+use poller::ChallengeWatch;
+use rom::RomCache;
+use scheduler::ChallengeScheduler;
+use telemetry::HashStats;
+use worker::AddressSlot;
+
+use ashmaize::Rom;
+
 use anyhow::{Context, Result};
 use tracing::{info, warn};
 
+use std::collections::HashSet;
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc,
 };
 
+/// How often pending outbox entries are retried while a round is mining.
+const OUTBOX_RETRY_EVERY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How `/register` receives the address's public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RegisterKey {
+    /// Raw 32-byte Ed25519 key (hex), what the Scavenger backend expects.
+    #[default]
+    Raw,
+    /// CBOR COSE_Key (hex), the `key` half of a CIP-30 `signData` result.
+    CoseKey,
+}
+
 pub struct Miner<P: AddressProvider + Clone> {
     client: ScavengerClient,
     provider: P,
     workers: usize,
+    parallel_addresses: usize,
     _network: Network,
 
     current_challenge_id: Arc<std::sync::Mutex<String>>,
     current_solutions: Arc<AtomicUsize>,
     global_solutions: Arc<AtomicUsize>,
 
+    roms: RomCache,
+    schedule: ChallengeScheduler,
+    hash_stats: Arc<HashStats>,
+    latest: ChallengeWatch,
+    poll_every: std::time::Duration,
+
     accounting: Accounting,
     donations: Donations,
+    outbox: Outbox,
+
+    shutdown: Shutdown,
 
     enable_donate: bool,
     donate_to: Option<String>,
+    register_key: RegisterKey,
+    /// Sign the blake2b-224 hash of register/donate messages (CIP-8).
+    cip8_hashed: bool,
 }
 
 impl<P: AddressProvider + Clone> Miner<P> {
+    /// Accounting, donations, outbox and challenges are kept in `keystore_dir`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         client: ScavengerClient,
         provider: P,
+        keystore_dir: &str,
         workers: Option<usize>,
+        parallel_addresses: usize,
         network: Network,
         enable_donate: bool,
         donate_to: Option<String>,
@@ -43,31 +90,81 @@ impl<P: AddressProvider + Clone> Miner<P> {
         let workers = workers
             .unwrap_or_else(|| std::thread::available_parallelism().map(|x| x.get()).unwrap_or(1));
 
-        let accounting = Accounting::new_from_env()
+        let accounting = Accounting::new(keystore_dir)
             .expect("failed to init accounting (keystore missing?)");
 
 
-        let donations = Donations::new_from_env()
+        let donations = Donations::new(keystore_dir)
             .expect("failed to init donations (keystore missing?)");
 
+        let outbox = Outbox::new(keystore_dir)
+            .expect("failed to init outbox (keystore missing?)");
+
+        let schedule = ChallengeScheduler::new(keystore_dir)
+            .expect("failed to init challenge scheduler (keystore missing?)");
+
         Self {
             client,
             provider,
             workers,
+            parallel_addresses,
             _network: network,
 
             current_challenge_id: Arc::new(std::sync::Mutex::new(String::new())),
             current_solutions: Arc::new(AtomicUsize::new(0)),
             global_solutions: Arc::new(AtomicUsize::new(0)),
 
+            roms: RomCache::new(),
+            schedule,
+            hash_stats: Arc::new(HashStats::new(workers)),
+            latest: ChallengeWatch::new(),
+            poll_every: poller::POLL_EVERY,
+
             accounting,
             donations,
+            outbox,
+
+            shutdown: Shutdown::new(),
 
             enable_donate,
             donate_to,
+            register_key: RegisterKey::default(),
+            cip8_hashed: false,
         }
     }
 
+    /// Stop mining when `shutdown` is triggered.
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Share a ROM cache (with an in-process mock server) instead of
+    /// building ROMs of its own.
+    #[cfg(test)]
+    pub fn with_roms(mut self, roms: RomCache) -> Self {
+        self.roms = roms;
+        self
+    }
+
+    /// Send the public key to `/register` as `register_key`.
+    pub fn with_register_key(mut self, register_key: RegisterKey) -> Self {
+        self.register_key = register_key;
+        self
+    }
+
+    /// Sign register and donate messages in CIP-8 hashed mode.
+    pub fn with_cip8_hashed(mut self, hashed: bool) -> Self {
+        self.cip8_hashed = hashed;
+        self
+    }
+
+    /// Poll `/challenge` every `every` while mining to catch rotations.
+    pub fn with_challenge_poll(mut self, every: std::time::Duration) -> Self {
+        self.poll_every = every;
+        self
+    }
+
     pub fn worker_count(&self) -> usize {
         self.workers
     }
@@ -78,6 +175,11 @@ impl<P: AddressProvider + Clone> Miner<P> {
             let _ = self.accounting.write_star_rates(&rates);
         }
 
+        // Everything we already have a receipt for is solved
+        for r in self.accounting.read_all_receipts()? {
+            self.schedule.mark_solved(&r.address, &r.challenge_id);
+        }
+
         if self.enable_donate {
             if let Some(dest) = &self.donate_to {
                 if !dest.is_empty() {
@@ -87,13 +189,136 @@ impl<P: AddressProvider + Clone> Miner<P> {
             }
         }
 
+        let started = std::time::Instant::now();
+        let reporter = self.hash_stats.clone().spawn_reporter(telemetry::REPORT_EVERY);
+        let poller = self.latest.spawn_poller(self.client.clone(), self.poll_every);
+        let result = self.mine_challenges(&tandc).await;
+        poller.abort();
+        reporter.abort();
+
+        if self.shutdown.is_triggered() {
+            self.log_session(started.elapsed());
+        }
+        result
+    }
+
+    /// Summary printed on shutdown.
+    fn log_session(&self, elapsed: std::time::Duration) {
+        let pending = self.outbox.pending().map(|p| p.len()).unwrap_or(0);
+        info!(
+            "Session ended after {} — {} solutions accepted, {} pending in outbox",
+            telemetry::fmt_duration(elapsed.as_secs_f64()),
+            self.global_solutions.load(Ordering::Relaxed),
+            pending
+        );
+        self.accounting.log_totals();
+    }
+
+    /// Log the expected NIGHT by the end of the mining period.
+    fn log_projection(&self, current_day: Option<u32>, max_day: Option<u32>, ch: &Challenge) {
+        let (Some(current_day), Some(max_day)) = (current_day, max_day) else {
+            tracing::debug!("No current_day/max_day from the server, skipping projection");
+            return;
+        };
+        let projection = (|| -> Result<crate::projection::Projection> {
+            let receipts = self.accounting.read_all_receipts()?;
+            let star_rates = self.accounting.read_star_rates()?;
+            let challenges = self.accounting.challenges()?;
+            crate::projection::project(&crate::projection::ProjectionInput {
+                receipts: &receipts,
+                star_rates: &star_rates,
+                challenges: &challenges,
+                current_day,
+                max_day,
+                current_difficulty: Some(&ch.difficulty),
+                hashrate: self.hash_stats.hashrate(),
+                now: chrono::Utc::now(),
+            })
+        })();
+        match projection {
+            Ok(p) => p.log(),
+            Err(e) => tracing::debug!("Projection unavailable: {e}"),
+        }
+    }
+
+    /// Resolves once the round mining `ch` should end early: on shutdown, or
+    /// when the server rotated and the scheduler now prefers another
+    /// challenge. Returns the reason for the log.
+    async fn preempted(&self, ch: &Challenge) -> String {
+        let mut rx = self.latest.subscribe();
+        rx.mark_unchanged();
+
+        loop {
+            tokio::select! {
+                _ = self.shutdown.wait() => return "shutdown requested".to_string(),
+                changed = rx.changed() => {
+                    if changed.is_err() {
+                        // Poller gone; only shutdown can end the round early
+                        self.shutdown.wait().await;
+                        return "shutdown requested".to_string();
+                    }
+                }
+            }
+
+            let Some(latest) = rx.borrow_and_update().clone() else { continue };
+            if latest.challenge_id == ch.challenge_id {
+                continue;
+            }
+
+            self.roms.prefetch(&latest.no_pre_mine);
+            if let Err(e) = self.schedule.observe(&latest) {
+                warn!("Failed to persist challenge {}: {e}", latest.challenge_id);
+            }
+
+            let known: Vec<String> = match self.provider.all_addresses() {
+                Ok(all) => all.into_iter().map(|a| a.address).collect(),
+                Err(e) => {
+                    warn!("Cannot list addresses to re-schedule: {e}");
+                    continue;
+                }
+            };
+            let star_rates = self.accounting.read_star_rates().unwrap_or_default();
+
+            match self.schedule.next_challenge(&latest.challenge_id, &known, &star_rates) {
+                Some(next) if next.challenge_id != ch.challenge_id => {
+                    return format!("challenge {} takes priority", next.challenge_id);
+                }
+                _ => info!(
+                    "New challenge {} published — staying on {} (still preferred)",
+                    latest.challenge_id, ch.challenge_id
+                ),
+            }
+        }
+    }
+
+    /// Sleep for `dur`, waking early on shutdown.
+    async fn pause(&self, dur: std::time::Duration) {
+        tokio::select! {
+            _ = tokio::time::sleep(dur) => {}
+            _ = self.shutdown.wait() => {}
+        }
+    }
+
+    async fn mine_challenges(&self, tandc: &TandCResponse) -> Result<()> {
         loop {
+            if self.shutdown.is_triggered() {
+                info!("Shutting down");
+                break;
+            }
+
+            // Solutions that could not be submitted yet (or from a previous run)
+            self.retry_outbox().await;
+
             let env = self.client.get_challenge().await?;
 
             match env.code.as_str() {
                 "active" => {
                     let ch = env.challenge.context("missing challenge")?;
                     let ch_id = ch.challenge_id.clone();
+                    self.latest.publish(&ch);
+
+                    // Kick off ROM generation early if no_pre_mine changed
+                    self.roms.prefetch(&ch.no_pre_mine);
 
                     //
                     // CHALLENGE CHANGE LOGIC
@@ -110,11 +335,45 @@ impl<P: AddressProvider + Clone> Miner<P> {
                                 let _ = self.accounting.write_star_rates(&rates);
                             }
 
-                            // Log totals
+                            // Log totals and where they are heading
                             self.accounting.log_totals();
+                            self.log_projection(env.current_day, env.max_day, &ch);
                         }
                     }
 
+                    //
+                    // SCHEDULE: the latest challenge, or an older one still
+                    // open for some of our addresses
+                    //
+                    if let Err(e) = self.schedule.observe(&ch) {
+                        warn!("Failed to persist challenge {}: {e}", ch_id);
+                    }
+
+                    let latest_id = ch_id;
+                    let known: Vec<String> = self
+                        .provider
+                        .all_addresses()?
+                        .into_iter()
+                        .map(|a| a.address)
+                        .collect();
+                    let star_rates = self.accounting.read_star_rates().unwrap_or_default();
+
+                    let ch = self
+                        .schedule
+                        .next_challenge(&latest_id, &known, &star_rates)
+                        .unwrap_or(ch);
+                    let ch_id = ch.challenge_id.clone();
+
+                    if ch_id != latest_id {
+                        info!(
+                            "Mining backlog challenge {} (day {}, open until {}) — {} open challenges",
+                            ch_id,
+                            ch.day,
+                            ch.latest_submission,
+                            self.schedule.open_count()
+                        );
+                    }
+
                     //
                     // LOG ADDRESS PROGRESS
                     //
@@ -129,58 +388,22 @@ impl<P: AddressProvider + Clone> Miner<P> {
                     );
 
                     //
-                    // ADDRESS SELECTION:
-                    // 1. iterate through existing addresses
-                    // 2. skip used ones
-                    // 3. if all used → generate new addresses
+                    // ADDRESS SELECTION
                     //
-                    let addr: AddressBundle = {
-                        let mut picked: Option<AddressBundle> = None;
-
-                        for _ in 0..total {
-                            let a = self.provider.next_address()?;
-
-                            match self.client.probe_solution(&a.address, &ch_id).await {
-                                Ok(true) => {
-                                    info!(
-                                        "Skipping address {} (already used for {})",
-                                        a.address, ch_id
-                                    );
-                                    continue;
-                                }
-                                Ok(false) => {
-                                    picked = Some(a);
-                                    break;
-                                }
-                                Err(e) => {
-                                    warn!("Probe failed for {}: {}", a.address, e);
-                                    continue;
-                                }
-                            }
-                        }
+                    let rom = self.roms.get(&ch.no_pre_mine).await?;
+                    self.hash_stats.start_challenge(&ch);
+
+                    if self.parallel_addresses > 1 {
+                        self.mine_parallel(tandc, &ch, &latest_id, rom).await?;
+                        continue;
+                    }
 
-                        match picked {
-                            Some(a) => a,
-                            None => loop {
-                                warn!(
-                                    "All existing addresses are used for {} — generating new address",
-                                    ch_id
-                                );
-
-                                let a = self.provider.new_address()?;
-
-                                match self.client.probe_solution(&a.address, &ch_id).await {
-                                    Ok(false) => break a,
-                                    Ok(true) => {
-                                        warn!("Fresh address unexpectedly marked used: {}", a.address);
-                                        continue;
-                                    }
-                                    Err(e) => {
-                                        warn!("Probe failed for new address {}: {}", a.address, e);
-                                        continue;
-                                    }
-                                }
-                            },
+                    let addr = match self.pick_address(&ch_id, &latest_id, &HashSet::new()).await? {
+                        Some(a) => a,
+                        None => {
+                            info!("No address left to mine backlog challenge {}", ch_id);
+                            self.schedule.mark_exhausted(&ch_id);
+                            continue;
                         }
                     };
 
@@ -188,76 +411,45 @@ impl<P: AddressProvider + Clone> Miner<P> {
                     // REGISTER ADDRESS
                     //
                     info!("Registering address {}", addr.address);
-                    self.register_address(&tandc, &addr).await?;
+                    self.register_address(tandc, &addr).await?;
                     info!("Registration OK");
 
                     //
                     // MINE
                     //
-                    let found = worker::mine_one_challenge(
+                    let stop = Arc::new(AtomicBool::new(false));
+                    let mining = worker::mine_one_challenge(
                         &self.provider,
                         &addr,
                         &ch,
+                        rom,
+                        self.hash_stats.clone(),
+                        stop.clone(),
                         self.workers,
-                    )
-                    .await?;
+                    );
+                    tokio::pin!(mining);
+                    let preempted = self.preempted(&ch);
+                    tokio::pin!(preempted);
+                    let mut outbox_tick = outbox_interval();
+
+                    let found = loop {
+                        tokio::select! {
+                            found = &mut mining => break found?,
+                            reason = &mut preempted => {
+                                info!("Stopping challenge {}: {reason}", ch_id);
+                                stop.store(true, Ordering::Relaxed);
+                                break mining.await?;
+                            }
+                            _ = outbox_tick.tick() => self.retry_outbox().await,
+                        }
+                    };
 
                     //
                     // SUBMIT
                     //
                     if let Some(nonce_hex) = found {
-                        let resp = self
-                            .client
-                            .submit_solution(&addr.address, &ch_id, &nonce_hex)
-                            .await?;
-
-                        self.current_solutions.fetch_add(1, Ordering::Relaxed);
-                        self.global_solutions.fetch_add(1, Ordering::Relaxed);
-
-                        //
-                        // STORE RECEIPT
-                        //
-                        let rec = ReceiptRecord {
-                            timestamp: resp.crypto_receipt.timestamp.clone(),
-                            address: addr.address.clone(),
-                            challenge_id: ch_id.clone(),
-                            day: ch.day,
-                            challenge_number: ch.challenge_number,
-                        };
-
-                        if let Err(e) = self.accounting.append_receipt(&rec) {
-                            warn!("Failed to persist receipt: {e}");
-                        }
-
-                        //
-                        // LOG OUTPUT
-                        //
-                        info!(
-                            "Challenge {} — index {}/{} — nonce={}",
-                            ch.challenge_number,
-                            self.provider.current_index().saturating_sub(1),
-                            total,
-                            nonce_hex
-                        );
-
-                        if self.enable_donate {
-                            if let Some(dest) = &self.donate_to {
-                                if !dest.is_empty() {
-                                    match self.perform_donate_to(dest, &addr).await {
-                                        Ok(()) => {
-                                            info!("Donated from {} → {}", addr.address, dest);
-                                        }
-                                        Err(e) => {
-                                            warn!("Failed donate_to from {} → {}: {}", addr.address, dest, e);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        // NIGHT estimate (all-time)
-                        self.accounting.log_totals();
-                    } else {
+                        self.submit_found(&ch, &addr, &nonce_hex).await?;
+                    } else if !self.shutdown.is_triggered() {
                         warn!("No solution found before next round / deadline");
                     }
                 }
@@ -266,7 +458,7 @@ impl<P: AddressProvider + Clone> Miner<P> {
                     if let Some(starts) = env.starts_at {
                         info!("Mining not started yet — begins at {}", starts);
                     }
-                    tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+                    self.pause(std::time::Duration::from_secs(10)).await;
                 }
 
                 "after" => {
@@ -276,11 +468,418 @@ impl<P: AddressProvider + Clone> Miner<P> {
 
                 other => {
                     warn!("Unknown challenge state {}", other);
-                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    self.pause(std::time::Duration::from_secs(5)).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pick an address that has not solved `ch_id` yet:
+    /// 1. iterate through existing addresses
+    /// 2. skip used ones (and the ones in `busy`, already being mined)
+    /// 3. if all used → generate new addresses, but only for the latest
+    ///    challenge; backlog challenges return `None` instead
+    async fn pick_address(
+        &self,
+        ch_id: &str,
+        latest_id: &str,
+        busy: &HashSet<String>,
+    ) -> Result<Option<AddressBundle>> {
+        let total = self.provider.total_addresses().max(1);
+
+        for _ in 0..total {
+            let a = self.provider.next_address()?;
+
+            if busy.contains(&a.address) || self.schedule.is_solved(&a.address, ch_id) {
+                continue;
+            }
+
+            match self.client.probe_solution(&a.address, ch_id).await {
+                Ok(true) => {
+                    self.schedule.mark_solved(&a.address, ch_id);
+                    info!(
+                        "Skipping address {} (already used for {})",
+                        a.address, ch_id
+                    );
+                    continue;
+                }
+                Ok(false) => return Ok(Some(a)),
+                Err(e) => {
+                    warn!("Probe failed for {}: {}", a.address, e);
+                    continue;
+                }
+            }
+        }
+
+        if ch_id != latest_id {
+            return Ok(None);
+        }
+
+        loop {
+            warn!(
+                "All existing addresses are used for {} — generating new address",
+                ch_id
+            );
+
+            let a = self.provider.new_address()?;
+
+            match self.client.probe_solution(&a.address, ch_id).await {
+                Ok(false) => return Ok(Some(a)),
+                Ok(true) => {
+                    warn!("Fresh address unexpectedly marked used: {}", a.address);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Probe failed for new address {}: {}", a.address, e);
+                    continue;
+                }
+            }
+        }
+    }
+
+    /// Persist a found nonce to the outbox, then submit it. On acceptance
+    /// the receipt is stored and the address donated if enabled; transient
+    /// failures leave it in the outbox for `flush_outbox`.
+    async fn submit_found(
+        &self,
+        ch: &Challenge,
+        addr: &AddressBundle,
+        nonce_hex: &str,
+    ) -> Result<()> {
+        let found = FoundSolution::new(ch, &addr.address, nonce_hex);
+        if let Err(e) = self.outbox.record(&found, SolutionStatus::Pending) {
+            warn!("Failed to persist solution to outbox: {e}");
+        }
+
+        if self.submit_pending(&found, false).await? {
+            //
+            // LOG OUTPUT
+            //
+            info!(
+                "Challenge {} — index {}/{} — nonce={}",
+                ch.challenge_number,
+                self.provider.current_index().saturating_sub(1),
+                self.provider.total_addresses().max(1),
+                nonce_hex
+            );
+
+            self.donate_solved(addr).await;
+
+            // NIGHT estimate (all-time)
+            self.accounting.log_totals();
+        }
+        Ok(())
+    }
+
+    /// `flush_outbox`, logging failures (the miner carries on regardless).
+    async fn retry_outbox(&self) {
+        if let Err(e) = self.flush_outbox().await {
+            warn!("Failed to flush solution outbox: {e}");
+        }
+    }
+
+    /// Retry every solution still pending in the outbox (also replays the
+    /// ones left over from a previous run). Expired ones are closed out.
+    async fn flush_outbox(&self) -> Result<()> {
+        let pending = self.outbox.pending()?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        info!("Retrying {} pending solutions from outbox", pending.len());
+
+        let mut accepted = 0;
+        for rec in pending {
+            let found = rec.solution;
+            if found.is_expired() {
+                warn!(
+                    "Solution for {} on {} expired before it was accepted",
+                    found.address, found.challenge_id
+                );
+                self.outbox.record(&found, SolutionStatus::Expired)?;
+                continue;
+            }
+
+            let submitted = matches!(rec.status, SolutionStatus::Submitted);
+            if self.submit_pending(&found, submitted).await? {
+                accepted += 1;
+                if let Some(addr) = self
+                    .provider
+                    .all_addresses()?
+                    .into_iter()
+                    .find(|a| a.address == found.address)
+                {
+                    self.donate_solved(&addr).await;
+                }
+            }
+        }
+
+        if accepted > 0 {
+            self.accounting.log_totals();
+        }
+        Ok(())
+    }
+
+    /// Submit one solution and record the outcome. Returns whether the
+    /// server accepted it; transient failures keep it pending.
+    ///
+    /// `submitted` is set when an earlier attempt (possibly in a previous
+    /// run) already sent it: "already solved" then means that attempt was
+    /// accepted and only its receipt got lost.
+    async fn submit_pending(&self, found: &FoundSolution, submitted: bool) -> Result<bool> {
+        let FoundSolution { address, challenge_id: ch_id, nonce, .. } = found;
+
+        if !submitted {
+            if let Err(e) = self.outbox.record(found, SolutionStatus::Submitted) {
+                warn!("Failed to mark solution as submitted in outbox: {e}");
+            }
+        }
+
+        let result = match self.client.submit_solution(address, ch_id, nonce).await {
+            Err(ApiError::AlreadySolved) if submitted => Err(ApiError::AcceptedWithoutReceipt),
+            other => other,
+        };
+        self.record_attempt(found, &result);
+
+        let (timestamp, crypto_receipt) = match result {
+            Ok(resp) => (resp.crypto_receipt.timestamp.clone(), Some(resp.crypto_receipt)),
+            Err(ApiError::AcceptedWithoutReceipt) => {
+                // Count it: the server holds our solution even though the
+                // receipt response got lost
+                warn!(
+                    "Solution for {} on {} accepted without receipt — recording local timestamp",
+                    address, ch_id
+                );
+                (chrono::Utc::now().to_rfc3339(), None)
+            }
+            Err(e @ (ApiError::Transport(_)
+            | ApiError::ServerError { .. }
+            | ApiError::RateLimited { .. })) => {
+                warn!("Submitting {} on {} failed, kept in outbox: {e}", address, ch_id);
+                return Ok(false);
+            }
+            Err(e) => {
+                if matches!(e, ApiError::AlreadySolved) {
+                    self.schedule.mark_solved(address, ch_id);
+                }
+                warn!("Solution for {} on {} rejected: {e}", address, ch_id);
+                self.outbox.record(found, SolutionStatus::Rejected { reason: e.to_string() })?;
+                return Ok(false);
+            }
+        };
+
+        self.current_solutions.fetch_add(1, Ordering::Relaxed);
+        self.global_solutions.fetch_add(1, Ordering::Relaxed);
+        self.schedule.mark_solved(address, ch_id);
+
+        //
+        // STORE RECEIPT
+        //
+        let rec = ReceiptRecord {
+            timestamp: timestamp.clone(),
+            address: address.clone(),
+            challenge_id: ch_id.clone(),
+            day: found.day,
+            challenge_number: found.challenge_number,
+            nonce: Some(nonce.clone()),
+            crypto_receipt,
+        };
+
+        if let Err(e) = self.accounting.append_receipt(&rec) {
+            warn!("Failed to persist receipt: {e}");
+        }
+        if let Err(e) = self.outbox.record(found, SolutionStatus::Accepted { timestamp }) {
+            warn!("Failed to record accepted solution in outbox: {e}");
+        }
+
+        Ok(true)
+    }
+
+    /// Log a `/solution` call in the accounting store.
+    fn record_attempt<T>(&self, found: &FoundSolution, result: &Result<T, ApiError>) {
+        let (outcome, detail) = match result {
+            Ok(_) => ("accepted", None),
+            Err(ApiError::AcceptedWithoutReceipt) => ("accepted_without_receipt", None),
+            Err(e @ (ApiError::Transport(_)
+            | ApiError::ServerError { .. }
+            | ApiError::RateLimited { .. })) => ("failed", Some(e.to_string())),
+            Err(e) => ("rejected", Some(e.to_string())),
+        };
+        if let Err(e) = self.accounting.record_attempt(
+            &found.address,
+            &found.challenge_id,
+            &found.nonce,
+            outcome,
+            detail.as_deref(),
+        ) {
+            warn!("Failed to record submission attempt: {e}");
+        }
+    }
+
+    async fn donate_solved(&self, addr: &AddressBundle) {
+        if self.enable_donate {
+            if let Some(dest) = &self.donate_to {
+                if !dest.is_empty() {
+                    match self.perform_donate_to(dest, addr).await {
+                        Ok(()) => {
+                            info!("Donated from {} → {}", addr.address, dest);
+                        }
+                        Err(e) => {
+                            warn!("Failed donate_to from {} → {}: {}", addr.address, dest, e);
+                        }
+                    }
                 }
             }
         }
+    }
+
+    /// Mine `ch` on up to `parallel_addresses` addresses at once, sharing one
+    /// ROM. Each solved address is replaced by the next unsolved one.
+    async fn mine_parallel(
+        &self,
+        tandc: &TandCResponse,
+        ch: &Challenge,
+        latest_id: &str,
+        rom: Arc<Rom>,
+    ) -> Result<()> {
+        let n = self.parallel_addresses.min(self.workers).max(1);
+        let slots: Arc<Vec<AddressSlot>> = Arc::new((0..n).map(|_| AddressSlot::new()).collect());
+        let mut mining: Vec<Option<AddressBundle>> = vec![None; n];
+
+        for (i, slot) in slots.iter().enumerate() {
+            let busy = busy_addresses(&mining);
+            let Some(a) = self.pick_address(&ch.challenge_id, latest_id, &busy).await? else {
+                break;
+            };
+            info!("Registering address {} (slot {})", a.address, i);
+            self.register_address(tandc, &a).await?;
+            slot.set(Some(&a));
+            mining[i] = Some(a);
+        }
+
+        if mining.iter().all(Option::is_none) {
+            info!("No address left to mine backlog challenge {}", ch.challenge_id);
+            self.schedule.mark_exhausted(&ch.challenge_id);
+            return Ok(());
+        }
+
+        info!(
+            "Challenge {} — mining {} addresses with {} workers",
+            ch.challenge_number,
+            mining.iter().flatten().count(),
+            self.workers
+        );
+
+        let mut running = worker::mine_addresses(
+            slots.clone(),
+            ch,
+            rom,
+            self.hash_stats.clone(),
+            self.workers,
+        );
+
+        let deadline = match chrono::DateTime::parse_from_rfc3339(&ch.latest_submission) {
+            // Already past: to_std fails and the round ends right away
+            Ok(d) => Some(
+                (d.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                    .to_std()
+                    .unwrap_or_default(),
+            ),
+            Err(e) => {
+                warn!(
+                    "Cannot parse deadline {:?} of challenge {}: {e} — mining without one",
+                    ch.latest_submission, ch.challenge_id
+                );
+                None
+            }
+        };
+        let timeout = async move {
+            match deadline {
+                Some(d) => tokio::time::sleep(d).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(timeout);
+        let preempted = self.preempted(ch);
+        tokio::pin!(preempted);
+        let mut outbox_tick = outbox_interval();
+
+        let result = loop {
+            let (i, nonce_hex) = tokio::select! {
+                found = running.solutions.recv() => match found {
+                    Some(found) => found,
+                    None => break Ok(()),
+                },
+                _ = &mut timeout => {
+                    warn!("Challenge {} deadline reached", ch.challenge_id);
+                    break Ok(());
+                }
+                reason = &mut preempted => {
+                    info!("Stopping challenge {}: {reason}", ch.challenge_id);
+                    break Ok(());
+                }
+                _ = outbox_tick.tick() => {
+                    self.retry_outbox().await;
+                    continue;
+                }
+            };
+
+            let Some(addr) = mining[i].take() else { continue };
+            if let Err(e) = self.submit_found(ch, &addr, &nonce_hex).await {
+                break Err(e);
+            }
+
+            // Replace the solved address with the next unsolved one
+            let busy = busy_addresses(&mining);
+            match self.pick_address(&ch.challenge_id, latest_id, &busy).await {
+                Ok(Some(a)) => {
+                    info!("Registering address {} (slot {})", a.address, i);
+                    if let Err(e) = self.register_address(tandc, &a).await {
+                        break Err(e);
+                    }
+                    slots[i].set(Some(&a));
+                    mining[i] = Some(a);
+                }
+                Ok(None) => {
+                    if mining.iter().all(Option::is_none) {
+                        info!("All addresses solved challenge {}", ch.challenge_id);
+                        break Ok(());
+                    }
+                }
+                Err(e) => break Err(e),
+            }
+        };
+
+        let settled = self.finish_round(ch, running, &mut mining).await;
+        result.and(settled)
+    }
 
+    /// Stop the workers of a `mine_parallel` round and settle the solutions
+    /// they sent that were not received yet: submitted while the challenge
+    /// is still open, otherwise kept in the outbox.
+    async fn finish_round(
+        &self,
+        ch: &Challenge,
+        running: worker::SlotMining,
+        mining: &mut [Option<AddressBundle>],
+    ) -> Result<()> {
+        for (i, nonce_hex) in running.stop().await {
+            let Some(addr) = mining[i].take() else { continue };
+            let found = FoundSolution::new(ch, &addr.address, &nonce_hex);
+            if found.is_expired() {
+                warn!(
+                    "Solution for {} on {} found as the challenge closed — kept in outbox",
+                    addr.address, ch.challenge_id
+                );
+                // Don't hand the address out for this challenge again
+                self.schedule.mark_solved(&addr.address, &ch.challenge_id);
+                self.outbox.record(&found, SolutionStatus::Pending)?;
+            } else {
+                info!("Submitting solution for {} found as the round stopped", addr.address);
+                self.submit_found(ch, &addr, &nonce_hex).await?;
+            }
+        }
         Ok(())
     }
 
@@ -289,7 +888,7 @@ impl<P: AddressProvider + Clone> Miner<P> {
         tandc: &TandCResponse,
         a: &AddressBundle,
     ) -> Result<()> {
-        use crate::util::cip8::cose_sign1_ed25519_with_headers;
+        use crate::util::cip8::{cose_key_ed25519, cose_sign1_ed25519_with_headers};
 
         let payload = tandc.message.trim_end();
 
@@ -297,31 +896,40 @@ impl<P: AddressProvider + Clone> Miner<P> {
             &a.privkey,
             payload,
             &a.address_raw,
-            false,
+            self.cip8_hashed,
         );
 
         let sig_hex = hex::encode(cose);
         let pub_hex = hex::encode(a.pubkey);
+        let key_hex = match self.register_key {
+            RegisterKey::Raw => pub_hex.clone(),
+            RegisterKey::CoseKey => hex::encode(cose_key_ed25519(&a.pubkey)),
+        };
 
-        self.client.register(&a.address, &sig_hex, &pub_hex).await?;
+        let receipt = self
+            .client
+            .register(&a.address, &sig_hex, &key_hex)
+            .await
+            .context("register failed")?;
+        if let Err(e) = self.accounting.record_registration(
+            &a.address,
+            &pub_hex,
+            &receipt.registrationReceipt,
+        ) {
+            warn!("Failed to record registration of {}: {e}", a.address);
+        }
         Ok(())
     }
 
     async fn perform_donate_to(&self, dest: &str, addr: &AddressBundle) -> Result<()> {
-        use crate::util::cip8::cose_sign1_ed25519_with_headers;
         use crate::util::cip8::cose_sign1_donate;
 
         // Donate signature payload is the *address itself*
         let payload = format!("Assign accumulated Scavenger rights to: {}", dest);
-        let cose = cose_sign1_donate(&addr.privkey, &payload);
+        let cose = cose_sign1_donate(&addr.privkey, &payload, self.cip8_hashed);
 
         let require_receipt = true;
-        let has_source_receipts = {
-            self.accounting
-                .read_all_receipts()?
-                .iter()
-                .any(|r| r.address == addr.address)
-        };
+        let has_source_receipts = self.accounting.has_receipts(&addr.address)?;
 
         self.donations.can_donate(
             &addr.address,
@@ -338,9 +946,22 @@ impl<P: AddressProvider + Clone> Miner<P> {
             .await?;
 
         info!("donate_to result: {}", resp);
+        self.record_donation(&addr.address, dest);
         Ok(())
     }
 
+    /// Log a donation the server accepted, so `can_donate` sees it next time.
+    fn record_donation(&self, source: &str, target: &str) {
+        let rec = DonationRecord {
+            source: source.to_string(),
+            target: target.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = self.donations.append_donation(&rec) {
+            warn!("Failed to record donation {} -> {}: {e}", source, target);
+        }
+    }
+
     pub async fn consolidate_all(&self, recipient: &str) -> Result<()> {
         use crate::util::cip8::cose_sign1_donate;
 
@@ -355,9 +976,6 @@ impl<P: AddressProvider + Clone> Miner<P> {
             recipient
         );
 
-        // Preload all receipts for fast lookup
-        let receipts = self.accounting.read_all_receipts()?;
-
         for addr in addresses {
             let donor = &addr.address;
 
@@ -368,7 +986,7 @@ impl<P: AddressProvider + Clone> Miner<P> {
             }
 
             // Has receipts?
-            let has_source_receipts = receipts.iter().any(|r| r.address == *donor);
+            let has_source_receipts = self.accounting.has_receipts(donor)?;
             if !has_source_receipts {
                 tracing::info!("Skipping {} (no receipts)", donor);
                 continue;
@@ -387,7 +1005,7 @@ impl<P: AddressProvider + Clone> Miner<P> {
 
             // Build the required message
             let payload = format!("Assign accumulated Scavenger rights to: {}", recipient);
-            let cose = cose_sign1_donate(&addr.privkey, &payload);
+            let cose = cose_sign1_donate(&addr.privkey, &payload, self.cip8_hashed);
             let sig_hex = hex::encode(cose);
 
             tracing::info!("Consolidating {} -> {}", donor, recipient);
@@ -395,13 +1013,7 @@ impl<P: AddressProvider + Clone> Miner<P> {
             match self.client.donate_to(recipient, donor, &sig_hex).await {
                 Ok(resp) => {
                     tracing::info!("donate_to success: {}", resp);
-                    // Write donation log
-                    let rec = DonationRecord {
-                        source: donor.clone(),
-                        target: recipient.to_string(),
-                        timestamp: chrono::Utc::now().to_rfc3339(),
-                    };
-                    let _ = self.donations.append_donation(&rec);
+                    self.record_donation(donor, recipient);
                 }
                 Err(e) => {
                     tracing::warn!("donate_to failed for {} -> {}: {}", donor, recipient, e);
@@ -412,4 +1024,73 @@ impl<P: AddressProvider + Clone> Miner<P> {
         tracing::info!("Startup consolidation completed.");
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Ticks every `OUTBOX_RETRY_EVERY`, first after one period.
+fn outbox_interval() -> tokio::time::Interval {
+    let start = tokio::time::Instant::now() + OUTBOX_RETRY_EVERY;
+    tokio::time::interval_at(start, OUTBOX_RETRY_EVERY)
+}
+
+fn busy_addresses(mining: &[Option<AddressBundle>]) -> HashSet<String> {
+    mining.iter().flatten().map(|a| a.address.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::prefill::PrefillProvider;
+    use crate::address::shelley::ShelleyProvider;
+    use crate::mock::{self, MockConfig};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn solution_sent_as_round_stops_is_submitted() {
+        // Every hash solves the challenge
+        let addr = mock::spawn(MockConfig {
+            difficulty: "FFFFFFFF".into(),
+            rotate_secs: 3600,
+            window_secs: 3600,
+            challenges_per_day: 24,
+            days: 21,
+            star_rate: 10_000_000,
+            error_rate: 0.0,
+        })
+        .await
+        .unwrap();
+        let client = ScavengerClient::new(format!("http://{addr}")).unwrap();
+        let tandc = client.get_tandc(None).await.unwrap();
+        let ch = client.get_challenge().await.unwrap().challenge.unwrap();
+
+        let keystore = std::env::temp_dir().join(format!("scavenger-late-{}", std::process::id()));
+        let keystore = keystore.to_str().unwrap();
+        let shelley = ShelleyProvider::new(Network::Preprod, keystore, None).await.unwrap();
+        let provider = PrefillProvider::new(shelley, keystore, None).unwrap();
+        let miner = Miner::new(client, provider, keystore, Some(1), 1, Network::Preprod, false, None);
+
+        let a = miner.provider.new_address().unwrap();
+        miner.register_address(&tandc, &a).await.unwrap();
+        let slots = Arc::new(vec![AddressSlot::new()]);
+        slots[0].set(Some(&a));
+        let mut mining = vec![Some(a.clone())];
+
+        miner.hash_stats.start_challenge(&ch);
+        let rom = miner.roms.get(&ch.no_pre_mine).await.unwrap();
+        let running = worker::mine_addresses(slots, &ch, rom, miner.hash_stats.clone(), 1);
+
+        // The worker counts its hashes after sending the nonce: once it has
+        // hashed, the solution is waiting in the channel. Preempt the round
+        // without receiving it.
+        while miner.hash_stats.hashrate().is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        miner.finish_round(&ch, running, &mut mining).await.unwrap();
+
+        let receipts = miner.accounting.read_all_receipts().unwrap();
+        assert!(receipts.iter().any(|r| r.address == a.address && r.challenge_id == ch.challenge_id));
+        assert!(miner.outbox.pending().unwrap().is_empty());
+        assert!(mining[0].is_none());
+
+        std::fs::remove_dir_all(keystore).unwrap();
+    }
+}
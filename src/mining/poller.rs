@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+use crate::api::{Challenge, ScavengerClient};
+
+/// How often `/challenge` is polled while workers are busy.
+pub const POLL_EVERY: Duration = Duration::from_secs(30);
+
+/// Latest active challenge seen by the miner loop or the background poller.
+///
+/// Only a change of `challenge_id` notifies subscribers, so a round can wait
+/// on `changed()` to learn about a rotation without polling itself.
+#[derive(Clone)]
+pub struct ChallengeWatch {
+    tx: Arc<watch::Sender<Option<Challenge>>>,
+}
+
+impl Default for ChallengeWatch {
+    fn default() -> Self {
+        let (tx, _rx) = watch::channel(None);
+        Self { tx: Arc::new(tx) }
+    }
+}
+
+impl ChallengeWatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `ch` as the latest challenge. Returns whether it is new.
+    pub fn publish(&self, ch: &Challenge) -> bool {
+        self.tx.send_if_modified(|cur| {
+            if cur.as_ref().map(|c| c.challenge_id.as_str()) == Some(ch.challenge_id.as_str()) {
+                return false;
+            }
+            *cur = Some(ch.clone());
+            true
+        })
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Option<Challenge>> {
+        self.tx.subscribe()
+    }
+
+    /// Poll `/challenge` every `every` and publish active challenges.
+    /// Errors are only logged; the miner loop surfaces persistent failures.
+    pub fn spawn_poller(&self, client: ScavengerClient, every: Duration) -> JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(every);
+            tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            tick.tick().await; // the miner loop just fetched it
+
+            loop {
+                tick.tick().await;
+                match client.get_challenge().await {
+                    Ok(env) if env.code == "active" => {
+                        if let Some(ch) = env.challenge {
+                            if this.publish(&ch) {
+                                info!(
+                                    "Server rotated to challenge {} (day {}, #{})",
+                                    ch.challenge_id, ch.day, ch.challenge_number
+                                );
+                            }
+                        }
+                    }
+                    Ok(env) => debug!("Challenge poll: state {}", env.code),
+                    Err(e) => debug!("Challenge poll failed: {e}"),
+                }
+            }
+        })
+    }
+}
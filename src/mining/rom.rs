@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+use ashmaize::{Rom, RomGenerationType};
+
+const ROM_SIZE: usize = 1_073_741_824; // 1 GiB
+const PRE_SIZE: usize = 16 * 1024 * 1024; // 16 MiB
+const MIXING: usize = 4;
+
+pub fn build_rom(no_pre_mine_ascii: &str) -> Rom {
+    let seed = no_pre_mine_ascii.as_bytes();
+
+    Rom::new(
+        seed,
+        RomGenerationType::TwoStep {
+            pre_size: PRE_SIZE,
+            mixing_numbers: MIXING,
+        },
+        ROM_SIZE,
+    )
+}
+
+type RomSlot = watch::Receiver<Option<Arc<Rom>>>;
+
+/// ROMs kept at once: two covers the day boundary, when the scheduler
+/// alternates between backlog challenges of yesterday and today's.
+const CACHED_ROMS: usize = 2;
+
+#[derive(Default)]
+struct RomCacheState {
+    /// Completed ROMs by `no_pre_mine`, least recently used first.
+    ready: VecDeque<(String, Arc<Rom>)>,
+    /// ROMs currently being generated in the background.
+    pending: HashMap<String, RomSlot>,
+}
+
+impl RomCacheState {
+    /// Cached ROM for `key`, marked as most recently used.
+    fn take_ready(&mut self, key: &str) -> Option<Arc<Rom>> {
+        let i = self.ready.iter().position(|(k, _)| k == key)?;
+        let entry = self.ready.remove(i)?;
+        let rom = entry.1.clone();
+        self.ready.push_back(entry);
+        Some(rom)
+    }
+
+    /// Install a finished ROM, dropping the least recently used one beyond
+    /// `CACHED_ROMS`.
+    fn install(&mut self, key: String, rom: Arc<Rom>) {
+        self.ready.retain(|(k, _)| k != &key);
+        self.ready.push_back((key, rom));
+        while self.ready.len() > CACHED_ROMS {
+            self.ready.pop_front();
+        }
+    }
+}
+
+/// Keeps the AshMaize ROMs for the most recent `no_pre_mine` values.
+///
+/// `no_pre_mine` only changes once per day, so a ROM is built once and
+/// shared by every challenge using the same seed; backlog challenges of the
+/// previous day keep theirs while today's is cached too. A new seed is built
+/// on a blocking thread; the cached ROMs stay alive meanwhile.
+///
+/// The cache is in-memory only, so every start pays for one `build_rom`.
+/// `ashmaize::Rom` keeps its data private and has no constructor taking
+/// existing bytes, so there is nothing to write to disk or map back in.
+#[derive(Clone, Default)]
+pub struct RomCache {
+    state: Arc<Mutex<RomCacheState>>,
+}
+
+impl RomCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start building the ROM for `no_pre_mine` in the background (no-op if
+    /// it is already cached or being built).
+    pub fn prefetch(&self, no_pre_mine: &str) {
+        let _ = self.slot(no_pre_mine);
+    }
+
+    /// Get the ROM for `no_pre_mine`, waiting for it to be built if needed.
+    pub async fn get(&self, no_pre_mine: &str) -> Result<Arc<Rom>> {
+        let mut rx = match self.slot(no_pre_mine) {
+            Ok(rom) => return Ok(rom),
+            Err(rx) => rx,
+        };
+
+        loop {
+            if let Some(rom) = rx.borrow_and_update().clone() {
+                return Ok(rom);
+            }
+            rx.changed()
+                .await
+                .map_err(|_| anyhow!("ROM generation for {} aborted", no_pre_mine))?;
+        }
+    }
+
+    /// Either the cached ROM, or a receiver for the in-flight build (which
+    /// is started here if nobody requested this seed yet).
+    fn slot(&self, no_pre_mine: &str) -> std::result::Result<Arc<Rom>, RomSlot> {
+        let mut st = self.state.lock().unwrap();
+
+        if let Some(rom) = st.take_ready(no_pre_mine) {
+            return Ok(rom);
+        }
+
+        if let Some(rx) = st.pending.get(no_pre_mine) {
+            return Err(rx.clone());
+        }
+
+        let (tx, rx) = watch::channel(None);
+        st.pending.insert(no_pre_mine.to_string(), rx.clone());
+
+        let key = no_pre_mine.to_string();
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || {
+            tracing::info!("Building ROM for no_pre_mine {}", key);
+            let started = std::time::Instant::now();
+            let rom = Arc::new(build_rom(&key));
+            tracing::info!("ROM for {} ready in {:.1?}", key, started.elapsed());
+
+            let mut st = state.lock().unwrap();
+            st.pending.remove(&key);
+            st.install(key, rom.clone());
+            let _ = tx.send(Some(rom));
+        });
+
+        Err(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn same_seed_shares_one_rom() {
+        let roms = RomCache::new();
+        let today = roms.get("seed-a").await.unwrap();
+        assert!(Arc::ptr_eq(&today, &roms.get("seed-a").await.unwrap()));
+
+        // Switching to another day's challenge keeps the first ROM cached
+        let yesterday = roms.get("seed-b").await.unwrap();
+        assert!(Arc::ptr_eq(&today, &roms.get("seed-a").await.unwrap()));
+        assert!(Arc::ptr_eq(&yesterday, &roms.get("seed-b").await.unwrap()));
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::api::types::Challenge;
+use crate::store::Store;
+
+/// Don't bother starting on a challenge that closes sooner than this.
+const MIN_REMAINING_SECS: i64 = 60;
+
+/// Remembers every challenge seen and picks which one to mine next.
+///
+/// Challenges stay solvable until their own `latest_submission`, so earlier
+/// ones are kept around (and persisted in the accounting store) and
+/// mined for every address that hasn't solved them yet.
+pub struct ChallengeScheduler {
+    store: Store,
+    challenges: Mutex<HashMap<String, Challenge>>,
+    /// (address, challenge_id) pairs known to be solved.
+    solved: Mutex<HashSet<(String, String)>>,
+    /// Backlog challenges no address could be picked for; skipped until the
+    /// next challenge is published.
+    exhausted: Mutex<HashSet<String>>,
+}
+
+impl ChallengeScheduler {
+    /// Construct using env var KEYSTORE (defaults to "keystore")
+    pub fn new_from_env() -> Result<Self> {
+        let root = std::env::var("KEYSTORE").unwrap_or_else(|_| "keystore".to_string());
+        Self::new(root)
+    }
+
+    /// Construct from explicit keystore directory, loading known challenges.
+    pub fn new<P: AsRef<Path>>(keystore_dir: P) -> Result<Self> {
+        let store = Store::new(keystore_dir)?;
+        let challenges = store
+            .challenges()?
+            .into_iter()
+            .map(|ch| (ch.challenge_id.clone(), ch))
+            .collect();
+
+        Ok(Self {
+            store,
+            challenges: Mutex::new(challenges),
+            solved: Mutex::new(HashSet::new()),
+            exhausted: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Record a challenge (persisted the first time it is seen).
+    pub fn observe(&self, ch: &Challenge) -> Result<()> {
+        let mut challenges = self.challenges.lock().unwrap();
+        if challenges.contains_key(&ch.challenge_id) {
+            return Ok(());
+        }
+
+        self.store.insert_challenge(ch)?;
+
+        tracing::info!(
+            "New challenge {} (day {}, difficulty {}, open until {})",
+            ch.challenge_id, ch.day, ch.difficulty, ch.latest_submission
+        );
+        challenges.insert(ch.challenge_id.clone(), ch.clone());
+        // New pass: give exhausted backlog challenges another chance
+        self.exhausted.lock().unwrap().clear();
+        Ok(())
+    }
+
+    pub fn mark_solved(&self, address: &str, challenge_id: &str) {
+        self.solved
+            .lock()
+            .unwrap()
+            .insert((address.to_string(), challenge_id.to_string()));
+    }
+
+    /// No address could be picked for `challenge_id` (all solved, or their
+    /// probes failed): don't schedule it again until a new challenge shows up.
+    pub fn mark_exhausted(&self, challenge_id: &str) {
+        self.exhausted.lock().unwrap().insert(challenge_id.to_string());
+    }
+
+    pub fn is_solved(&self, address: &str, challenge_id: &str) -> bool {
+        self.solved
+            .lock()
+            .unwrap()
+            .contains(&(address.to_string(), challenge_id.to_string()))
+    }
+
+    /// Number of challenges that are still open for submissions.
+    pub fn open_count(&self) -> usize {
+        let now = chrono::Utc::now();
+        self.challenges
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|c| remaining_secs(c, now) > 0)
+            .count()
+    }
+
+    /// Pick the next challenge to mine.
+    ///
+    /// Candidates are open challenges that at least one of `addresses` has
+    /// not solved and that are not exhausted; the `latest_id` challenge is
+    /// always a candidate because new addresses can be generated for it.
+    /// Ordered by STAR rate of the challenge's day (unpublished days use the
+    /// latest known rate), then by least remaining time.
+    pub fn next_challenge(
+        &self,
+        latest_id: &str,
+        addresses: &[String],
+        star_rates: &[u64],
+    ) -> Option<Challenge> {
+        let now = chrono::Utc::now();
+        let challenges = self.challenges.lock().unwrap();
+        let solved = self.solved.lock().unwrap();
+        let exhausted = self.exhausted.lock().unwrap();
+
+        let fallback_rate = star_rates.last().copied().unwrap_or(0);
+        let rate_of = |day: u32| -> u64 {
+            (day as usize)
+                .checked_sub(1)
+                .and_then(|i| star_rates.get(i))
+                .copied()
+                .unwrap_or(fallback_rate)
+        };
+
+        let mut candidates: Vec<(u64, i64, &Challenge)> = challenges
+            .values()
+            .filter_map(|c| {
+                let remaining = remaining_secs(c, now);
+                if remaining < MIN_REMAINING_SECS {
+                    return None;
+                }
+                let open_for_someone = c.challenge_id == latest_id
+                    || (!exhausted.contains(&c.challenge_id)
+                        && addresses
+                            .iter()
+                            .any(|a| !solved.contains(&(a.clone(), c.challenge_id.clone()))));
+                open_for_someone.then(|| (rate_of(c.day), remaining, c))
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        candidates.first().map(|(_, _, c)| (*c).clone())
+    }
+}
+
+fn remaining_secs(ch: &Challenge, now: chrono::DateTime<chrono::Utc>) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(&ch.latest_submission)
+        .map(|d| (d.with_timezone(&chrono::Utc) - now).num_seconds())
+        .unwrap_or(0)
+}
@@ -0,0 +1,218 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api::types::Challenge;
+
+/// How often the hashrate line is logged.
+pub const REPORT_EVERY: Duration = Duration::from_secs(30);
+
+struct Current {
+    challenge_id: String,
+    difficulty: String,
+    started: Instant,
+    /// Bumped whenever the counters are reset for a new challenge.
+    generation: u64,
+}
+
+/// Counters as seen by one report.
+struct Sample {
+    generation: u64,
+    started: Instant,
+    at: Instant,
+    counts: Vec<u64>,
+}
+
+impl Sample {
+    /// H/s per thread since `prev`. After a reset for a new challenge the
+    /// counters run from that challenge's start instead.
+    fn rates_since(&self, prev: &Sample) -> Vec<f64> {
+        let (base, since) = if self.generation == prev.generation {
+            (prev.counts.as_slice(), prev.at)
+        } else {
+            (&[][..], self.started)
+        };
+        let secs = self.at.duration_since(since).as_secs_f64().max(f64::EPSILON);
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, n)| n.saturating_sub(base.get(i).copied().unwrap_or(0)) as f64 / secs)
+            .collect()
+    }
+}
+
+/// Per-thread hash counters for the challenge currently being mined.
+pub struct HashStats {
+    per_thread: Vec<AtomicU64>,
+    current: Mutex<Current>,
+}
+
+impl HashStats {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            per_thread: (0..threads.max(1)).map(|_| AtomicU64::new(0)).collect(),
+            current: Mutex::new(Current {
+                challenge_id: String::new(),
+                difficulty: String::new(),
+                started: Instant::now(),
+                generation: 0,
+            }),
+        }
+    }
+
+    /// Reset the counters if `ch` is not the challenge being counted already.
+    pub fn start_challenge(&self, ch: &Challenge) {
+        let mut cur = self.current.lock().unwrap();
+        if cur.challenge_id == ch.challenge_id {
+            return;
+        }
+        for c in &self.per_thread {
+            c.store(0, Ordering::Relaxed);
+        }
+        *cur = Current {
+            challenge_id: ch.challenge_id.clone(),
+            difficulty: ch.difficulty.clone(),
+            started: Instant::now(),
+            generation: cur.generation + 1,
+        };
+    }
+
+    #[inline]
+    pub fn add(&self, thread: usize, hashes: u64) {
+        if let Some(c) = self.per_thread.get(thread) {
+            c.fetch_add(hashes, Ordering::Relaxed);
+        }
+    }
+
+    /// Average hashrate (H/s) on the current challenge, if it hashed at all.
+    pub fn hashrate(&self) -> Option<f64> {
+        let started = self.current.lock().unwrap().started;
+        let hashes: u64 = self.snapshot().iter().sum();
+        let secs = started.elapsed().as_secs_f64();
+        (hashes > 0 && secs > 0.0).then(|| hashes as f64 / secs)
+    }
+
+    fn snapshot(&self) -> Vec<u64> {
+        self.per_thread.iter().map(|c| c.load(Ordering::Relaxed)).collect()
+    }
+
+    fn sample(&self) -> Sample {
+        // Under the lock, so a reset can't land between generation and counts
+        let cur = self.current.lock().unwrap();
+        Sample {
+            generation: cur.generation,
+            started: cur.started,
+            at: Instant::now(),
+            counts: self.snapshot(),
+        }
+    }
+
+    /// Log hashrate and progress every `every` until the task is aborted.
+    /// Intervals without any hashing (waiting for a challenge) are skipped.
+    pub fn spawn_reporter(self: Arc<Self>, every: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut prev = self.sample();
+
+            loop {
+                tokio::time::sleep(every).await;
+
+                let now = self.sample();
+                let rates = now.rates_since(&prev);
+                prev = now;
+
+                let total_rate: f64 = rates.iter().sum();
+                if total_rate <= 0.0 {
+                    continue;
+                }
+                let min = rates.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = rates.iter().cloned().fold(0.0, f64::max);
+
+                let cur = self.current.lock().unwrap();
+                let tried: u64 = prev.counts.iter().sum();
+                let expected = expected_hashes(&cur.difficulty);
+
+                tracing::info!(
+                    "Hashrate {:.1} H/s ({} threads, {:.1}–{:.1} H/s each) — {} hashes on {} in {} — expected {:.0} hashes per solution, ETA {}",
+                    total_rate,
+                    rates.len(),
+                    min,
+                    max,
+                    tried,
+                    cur.challenge_id,
+                    fmt_duration(cur.started.elapsed().as_secs_f64()),
+                    expected,
+                    fmt_duration(expected / total_rate),
+                );
+                tracing::debug!(
+                    "Per-thread H/s: {}",
+                    rates.iter().map(|r| format!("{r:.1}")).collect::<Vec<_>>().join(" ")
+                );
+            }
+        })
+    }
+}
+
+/// Expected number of hashes to find a solution.
+///
+/// A hash matches when `(h | d) == d` on the first 32 bits, i.e. every zero
+/// bit of the difficulty mask must be zero in the hash.
+pub fn expected_hashes(difficulty: &str) -> f64 {
+    let zero_bits = difficulty
+        .get(0..8)
+        .and_then(|d| u32::from_str_radix(d, 16).ok())
+        .map(|d| d.count_zeros())
+        .unwrap_or(0);
+    2f64.powi(zero_bits as i32)
+}
+
+pub fn fmt_duration(secs: f64) -> String {
+    if !secs.is_finite() {
+        return "∞".to_string();
+    }
+    let s = secs.round() as u64;
+    match s {
+        0..=59 => format!("{s}s"),
+        60..=3599 => format!("{}m{:02}s", s / 60, s % 60),
+        _ => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(id: &str) -> Challenge {
+        Challenge {
+            challenge_id: id.into(),
+            day: 1,
+            challenge_number: 1,
+            issued_at: String::new(),
+            latest_submission: String::new(),
+            difficulty: "0FFFFFFF".into(),
+            no_pre_mine: String::new(),
+            no_pre_mine_hour: String::new(),
+        }
+    }
+
+    #[test]
+    fn new_challenge_does_not_zero_the_next_interval() {
+        let stats = HashStats::new(2);
+        stats.start_challenge(&challenge("**D01C01"));
+        stats.add(0, 1_000);
+        stats.add(1, 1_000);
+        let prev = stats.sample();
+
+        // Fewer hashes on the new challenge than the old totals
+        stats.start_challenge(&challenge("**D01C02"));
+        stats.add(0, 10);
+        stats.add(1, 20);
+        std::thread::sleep(Duration::from_millis(10));
+        let rates = stats.sample().rates_since(&prev);
+        assert!(rates.iter().all(|r| *r > 0.0), "{rates:?}");
+
+        // Same challenge: only the hashes since the previous sample count
+        let prev = stats.sample();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(stats.sample().rates_since(&prev), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,297 @@
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::accounting::ReceiptRecord;
+use crate::api::types::Challenge;
+use super::rom::build_rom;
+use super::worker::{ash_hash, build_preimage, matches_diff};
+
+/// Result of one check on a receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    Ok,
+    Failed,
+    /// Could not be checked (missing data, no server key, or `--no-hash`).
+    Skipped,
+}
+
+impl Check {
+    fn label(self) -> &'static str {
+        match self {
+            Check::Ok => "ok",
+            Check::Failed => "FAILED",
+            Check::Skipped => "-",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReceiptCheck {
+    pub address: String,
+    pub challenge_id: String,
+    pub nonce: Option<String>,
+    /// Preimage is the one we submitted for address/challenge/nonce
+    pub preimage: Check,
+    /// Server signature over the preimage verifies
+    pub signature: Check,
+    /// AshMaize hash of the preimage meets the challenge difficulty
+    pub difficulty: Check,
+    /// Why the receipt would not hold up in a dispute (empty if it would)
+    pub problems: Vec<String>,
+}
+
+impl ReceiptCheck {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub failed: usize,
+    pub signatures_checked: bool,
+    pub hashes_checked: bool,
+    pub receipts: Vec<ReceiptCheck>,
+}
+
+/// Check every receipt against what we submitted, the server key and the
+/// challenge difficulty. With `hash`, the ROM is built once per distinct
+/// `no_pre_mine` (slow: about as long as at mining start).
+pub fn verify_receipts(
+    receipts: &[ReceiptRecord],
+    challenges: &[Challenge],
+    server_key: Option<&VerifyingKey>,
+    hash: bool,
+) -> VerifyReport {
+    let challenges: HashMap<&str, &Challenge> =
+        challenges.iter().map(|c| (c.challenge_id.as_str(), c)).collect();
+
+    let mut checks: Vec<ReceiptCheck> = receipts
+        .iter()
+        .map(|r| check_receipt(r, challenges.get(r.challenge_id.as_str()).copied(), server_key))
+        .collect();
+
+    if hash {
+        // Receipts with a verified preimage, grouped by ROM seed
+        let mut by_seed: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, r) in receipts.iter().enumerate() {
+            if checks[i].preimage == Check::Ok {
+                let ch = challenges[r.challenge_id.as_str()];
+                by_seed.entry(ch.no_pre_mine.as_str()).or_default().push(i);
+            }
+        }
+
+        for (seed, idxs) in by_seed {
+            tracing::info!("Building ROM for no_pre_mine {} ({} receipts)…", seed, idxs.len());
+            let rom = build_rom(seed);
+            for i in idxs {
+                let r = &receipts[i];
+                let ch = challenges[r.challenge_id.as_str()];
+                let preimage = &r.crypto_receipt.as_ref().expect("checked preimage").preimage;
+                let digest = ash_hash(preimage.as_bytes(), &rom);
+                if matches_diff(&digest, &ch.difficulty) {
+                    checks[i].difficulty = Check::Ok;
+                } else {
+                    checks[i].difficulty = Check::Failed;
+                    checks[i].problems.push(format!(
+                        "hash {} does not meet difficulty {}",
+                        hex::encode(&digest[..4]),
+                        ch.difficulty
+                    ));
+                }
+            }
+        }
+    }
+
+    VerifyReport {
+        checked: checks.len(),
+        failed: checks.iter().filter(|c| !c.is_ok()).count(),
+        signatures_checked: server_key.is_some(),
+        hashes_checked: hash,
+        receipts: checks,
+    }
+}
+
+/// Preimage and signature checks (everything but the hash).
+fn check_receipt(
+    r: &ReceiptRecord,
+    ch: Option<&Challenge>,
+    server_key: Option<&VerifyingKey>,
+) -> ReceiptCheck {
+    let mut c = ReceiptCheck {
+        address: r.address.clone(),
+        challenge_id: r.challenge_id.clone(),
+        nonce: r.nonce.clone(),
+        preimage: Check::Skipped,
+        signature: Check::Skipped,
+        difficulty: Check::Skipped,
+        problems: Vec::new(),
+    };
+
+    let Some(receipt) = &r.crypto_receipt else {
+        c.problems.push("no server receipt stored".into());
+        return c;
+    };
+
+    match (&r.nonce, ch) {
+        (None, _) => c.problems.push("submitted nonce not recorded".into()),
+        (Some(_), None) => c.problems.push("challenge not in the accounting store".into()),
+        (Some(nonce), Some(ch)) => {
+            let expected = build_preimage(
+                nonce,
+                &r.address,
+                &ch.challenge_id,
+                &ch.difficulty,
+                &ch.no_pre_mine,
+                &ch.latest_submission,
+                &ch.no_pre_mine_hour,
+            );
+            if receipt.preimage == expected {
+                c.preimage = Check::Ok;
+            } else {
+                c.preimage = Check::Failed;
+                c.problems.push("preimage does not match the submitted address/challenge/nonce".into());
+            }
+        }
+    }
+
+    if let Some(key) = server_key {
+        match verify_signature(key, &receipt.preimage, &receipt.signature) {
+            Ok(()) => c.signature = Check::Ok,
+            Err(e) => {
+                c.signature = Check::Failed;
+                c.problems.push(format!("server signature invalid: {e}"));
+            }
+        }
+    }
+
+    c
+}
+
+/// Ed25519 signature (hex) over the UTF-8 preimage.
+fn verify_signature(key: &VerifyingKey, preimage: &str, sig_hex: &str) -> anyhow::Result<()> {
+    let sig: [u8; 64] = hex::decode(sig_hex)?
+        .try_into()
+        .map_err(|b: Vec<u8>| anyhow::anyhow!("signature must be 64 bytes, got {}", b.len()))?;
+    key.verify(preimage.as_bytes(), &Signature::from_bytes(&sig))?;
+    Ok(())
+}
+
+impl VerifyReport {
+    /// Failing receipts, then a summary.
+    pub fn print_table(&self) {
+        if self.failed > 0 {
+            println!(
+                "{:<12} {:<8} {:<8} {:<8} {:<16} address / problems",
+                "challenge", "preimage", "sig", "hash", "nonce"
+            );
+            for c in self.receipts.iter().filter(|c| !c.is_ok()) {
+                println!(
+                    "{:<12} {:<8} {:<8} {:<8} {:<16} {}",
+                    c.challenge_id,
+                    c.preimage.label(),
+                    c.signature.label(),
+                    c.difficulty.label(),
+                    c.nonce.as_deref().unwrap_or("-"),
+                    c.address
+                );
+                for p in &c.problems {
+                    println!("{:>56}{}", "", p);
+                }
+            }
+            println!();
+        }
+
+        println!(
+            "{} receipts checked, {} would not hold up in a dispute",
+            self.checked, self.failed
+        );
+        if !self.signatures_checked {
+            println!("Server signatures not checked (pass --server-pubkey)");
+        }
+        if !self.hashes_checked {
+            println!("AshMaize hashes not checked (--no-hash)");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::types::CryptoReceipt;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn challenge() -> Challenge {
+        Challenge {
+            challenge_id: "**D06C22".into(),
+            day: 6,
+            challenge_number: 22,
+            issued_at: "2025-11-04T21:00:00Z".into(),
+            latest_submission: "2025-11-05T21:00:00Z".into(),
+            difficulty: "000FFFFF".into(),
+            no_pre_mine: "fd651ac2725e3b9d".into(),
+            no_pre_mine_hour: "509681483".into(),
+        }
+    }
+
+    fn server_key() -> SigningKey {
+        SigningKey::from_bytes(&[5; 32])
+    }
+
+    /// Receipt for `nonce`, signed by the server key over `preimage_nonce`'s preimage.
+    fn receipt(nonce: &str, preimage_nonce: &str) -> ReceiptRecord {
+        let ch = challenge();
+        let preimage = build_preimage(
+            preimage_nonce,
+            "addr1",
+            &ch.challenge_id,
+            &ch.difficulty,
+            &ch.no_pre_mine,
+            &ch.latest_submission,
+            &ch.no_pre_mine_hour,
+        );
+        let signature = hex::encode(server_key().sign(preimage.as_bytes()).to_bytes());
+        ReceiptRecord {
+            timestamp: "2025-11-04T21:26:06Z".into(),
+            address: "addr1".into(),
+            challenge_id: ch.challenge_id.clone(),
+            day: ch.day,
+            challenge_number: ch.challenge_number,
+            nonce: Some(nonce.into()),
+            crypto_receipt: Some(CryptoReceipt { preimage, timestamp: "2025-11-04T21:26:06Z".into(), signature }),
+        }
+    }
+
+    #[test]
+    fn matching_receipt_passes() {
+        let key = server_key().verifying_key();
+        let c = check_receipt(&receipt("00000000deadbeef", "00000000deadbeef"), Some(&challenge()), Some(&key));
+        assert_eq!((c.preimage, c.signature, c.difficulty), (Check::Ok, Check::Ok, Check::Skipped));
+        assert!(c.is_ok(), "{:?}", c.problems);
+    }
+
+    #[test]
+    fn mismatched_nonce_fails_the_preimage() {
+        let key = server_key().verifying_key();
+        let c = check_receipt(&receipt("00000000deadbeef", "00000000cafebabe"), Some(&challenge()), Some(&key));
+        assert_eq!((c.preimage, c.signature), (Check::Failed, Check::Ok));
+        assert!(!c.is_ok());
+    }
+
+    #[test]
+    fn bad_signature_fails() {
+        let r = receipt("00000000deadbeef", "00000000deadbeef");
+        let other = SigningKey::from_bytes(&[6; 32]).verifying_key();
+        let c = check_receipt(&r, Some(&challenge()), Some(&other));
+        assert_eq!((c.preimage, c.signature), (Check::Ok, Check::Failed));
+
+        let receipt = r.crypto_receipt.unwrap();
+        let key = server_key().verifying_key();
+        assert!(verify_signature(&key, &receipt.preimage, &receipt.signature).is_ok());
+        assert!(verify_signature(&key, "tampered", &receipt.signature).is_err());
+        assert!(verify_signature(&key, &receipt.preimage, "abcd").is_err());
+    }
+}
@@ -3,40 +3,25 @@ use rand_chacha::{
     rand_core::{RngCore, SeedableRng},
     ChaCha12Rng,
 };
-use std::sync::{mpsc, Arc};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
-use ashmaize::{Rom, RomGenerationType, hash};
+use ashmaize::{Rom, hash};
 
 use crate::address::{AddressBundle, AddressProvider};
 use crate::api::types::Challenge;
+use super::telemetry::HashStats;
 
 const LOOPS: u32 = 8;
 const INSTR: u32 = 256;
 
-fn build_rom(no_pre_mine_ascii: &str) -> Rom {
-    let seed = no_pre_mine_ascii.as_bytes();
-    const ROM_SIZE: usize = 1_073_741_824; // 1 GiB
-    const PRE_SIZE: usize = 16 * 1024 * 1024; // 16 MiB
-    const MIXING: usize = 4;
-
-    Rom::new(
-        &seed,
-        RomGenerationType::TwoStep {
-            pre_size: PRE_SIZE,
-            mixing_numbers: MIXING,
-        },
-        ROM_SIZE,
-    )
-}
-
 #[inline]
-fn ash_hash(preimage: &[u8], rom: &Rom) -> [u8; 64] {
+pub(crate) fn ash_hash(preimage: &[u8], rom: &Rom) -> [u8; 64] {
     hash(preimage, rom, LOOPS, INSTR)
 }
 
 #[inline]
-fn matches_diff(h: &[u8; 64], diff: &str) -> bool {
+pub(crate) fn matches_diff(h: &[u8; 64], diff: &str) -> bool {
     // Compare first 4 bytes (big-endian semantics) with the mask rule `(h | d) == d`
     let h_hex = hex::encode(&h[0..4]);
     let h_val = u32::from_str_radix(&h_hex, 16).unwrap();
@@ -44,7 +29,7 @@ fn matches_diff(h: &[u8; 64], diff: &str) -> bool {
     (h_val | d_val) == d_val
 }
 
-fn build_preimage(
+pub(crate) fn build_preimage(
     nonce_hex: &str,
     address: &str,
     challenge_id: &str,
@@ -72,20 +57,22 @@ fn build_preimage(
     s
 }
 
+/// Mine `ch` for one address until a solution is found, the deadline
+/// passes or `stop` is set (shutdown or the round was preempted).
 pub async fn mine_one_challenge<P: AddressProvider + Clone>(
     _provider: &P,
     addr: &AddressBundle,
     ch: &Challenge,
+    rom: Arc<Rom>,
+    stats: Arc<HashStats>,
+    stop: Arc<AtomicBool>,
     workers: usize,
 ) -> Result<Option<String>> {
-    // Build ROM once and share
-    let rom = Arc::new(build_rom(&ch.no_pre_mine));
-
     // Signal to stop all workers as soon as one finds a solution
     let found_flag = Arc::new(AtomicBool::new(false));
 
     // Channel to get the winning nonce
-    let (tx_winner, rx_winner) = mpsc::channel::<[u8; 8]>();
+    let (tx_winner, mut rx_winner) = tokio::sync::mpsc::unbounded_channel::<[u8; 8]>();
 
     // Parse deadline if present
     let deadline = chrono::DateTime::parse_from_rfc3339(&ch.latest_submission)
@@ -103,7 +90,9 @@ pub async fn mine_one_challenge<P: AddressProvider + Clone>(
 
     for worker_id in 0..workers {
         let rom = rom.clone();
+        let stats = stats.clone();
         let found_flag = found_flag.clone();
+        let stop = stop.clone();
         let tx_winner = tx_winner.clone();
 
         let address = address.clone();
@@ -124,7 +113,7 @@ pub async fn mine_one_challenge<P: AddressProvider + Clone>(
             // Optionally batch a few iterations between checks for throughput
             const BATCH: usize = 256;
             loop {
-                if found_flag.load(Ordering::Relaxed) {
+                if found_flag.load(Ordering::Relaxed) || stop.load(Ordering::Relaxed) {
                     return None;
                 }
                 if let Some(dead) = deadline {
@@ -133,7 +122,7 @@ pub async fn mine_one_challenge<P: AddressProvider + Clone>(
                     }
                 }
 
-                for _ in 0..BATCH {
+                for i in 0..BATCH {
                     // Make a 64-bit nonce
                     let mut nonce = [0u8; 8];
                     rng.fill_bytes(&mut nonce);
@@ -153,28 +142,37 @@ pub async fn mine_one_challenge<P: AddressProvider + Clone>(
                     let digest = ash_hash(preimage.as_bytes(), &rom);
 
                     if matches_diff(&digest, &difficulty) {
+                        stats.add(worker_id, i as u64 + 1);
                         // Announce and stop others
                         found_flag.store(true, Ordering::Relaxed);
                         let _ = tx_winner.send(nonce);
                         return Some(nonce);
                     }
                 }
+                stats.add(worker_id, BATCH as u64);
             }
         }));
     }
 
-    // Wait for winner or deadline timeout
-    let maybe_nonce = if let Some(dead) = deadline {
-        let now = chrono::Utc::now();
-        if dead > now {
-            let dur = (dead - now).to_std().unwrap_or_default();
-            rx_winner.recv_timeout(dur).ok()
-        } else {
-            None
+    // Wait for winner, deadline timeout or stop
+    const POLL: std::time::Duration = std::time::Duration::from_millis(250);
+    let maybe_nonce = loop {
+        if stop.load(Ordering::Relaxed) {
+            // A winner may have raced the stop signal
+            break rx_winner.try_recv().ok();
+        }
+        let wait = match deadline {
+            Some(dead) => match (dead - chrono::Utc::now()).to_std() {
+                Ok(left) => left.min(POLL),
+                Err(_) => break None, // deadline passed
+            },
+            None => POLL,
+        };
+        if let Ok(nonce) = tokio::time::timeout(wait, rx_winner.recv()).await {
+            break nonce;
         }
-    } else {
-        rx_winner.recv().ok()
     };
+    found_flag.store(true, Ordering::Relaxed);
 
     // Ensure all threads exit
     for t in threads {
@@ -182,4 +180,216 @@ pub async fn mine_one_challenge<P: AddressProvider + Clone>(
     }
 
     Ok(maybe_nonce.map(hex::encode))
-}
\ No newline at end of file
+}
+
+/// The address mined by one share of the worker threads.
+///
+/// `generation` is bumped on every change so threads only take the lock
+/// when the address was swapped; a thread may only claim a solution for the
+/// generation it mined, which keeps one solution per address.
+pub struct AddressSlot {
+    address: Mutex<Option<String>>,
+    generation: AtomicU64,
+}
+
+impl AddressSlot {
+    pub fn new() -> Self {
+        Self {
+            address: Mutex::new(None),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Point the slot at a new address (or park its threads with `None`).
+    pub fn set(&self, addr: Option<&AddressBundle>) {
+        let mut a = self.address.lock().unwrap();
+        *a = addr.map(|a| a.address.clone());
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    fn load(&self) -> (u64, Option<String>) {
+        let a = self.address.lock().unwrap();
+        (self.generation.load(Ordering::Acquire), a.clone())
+    }
+
+    /// Claim a solution found for `generation`; parks the slot on success.
+    fn claim(&self, generation: u64) -> bool {
+        let mut a = self.address.lock().unwrap();
+        if self.generation.load(Ordering::Acquire) != generation || a.is_none() {
+            return false;
+        }
+        *a = None;
+        self.generation.fetch_add(1, Ordering::Release);
+        true
+    }
+}
+
+/// Running worker threads started by [`mine_addresses`].
+pub struct SlotMining {
+    /// Winning nonces as `(slot index, nonce_hex)`.
+    pub solutions: tokio::sync::mpsc::UnboundedReceiver<(usize, String)>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl SlotMining {
+    /// Signal all threads to stop and wait for them to exit (joined on a
+    /// blocking thread, a batch of hashes can take a while to finish).
+    ///
+    /// Returns the solutions not received from `solutions` yet: a thread may
+    /// claim one (parking its slot) right before the round ends.
+    pub async fn stop(mut self) -> Vec<(usize, String)> {
+        self.stop.store(true, Ordering::Relaxed);
+        let threads = std::mem::take(&mut self.threads);
+        let _ = tokio::task::spawn_blocking(move || {
+            for t in threads {
+                let _ = t.join();
+            }
+        })
+        .await;
+
+        std::iter::from_fn(|| self.solutions.try_recv().ok()).collect()
+    }
+}
+
+/// Spread `workers` threads across `slots`, all sharing one ROM.
+///
+/// After a win the slot is parked until the caller sets the next address.
+/// Threads run until stopped or the challenge deadline passes.
+pub fn mine_addresses(
+    slots: Arc<Vec<AddressSlot>>,
+    ch: &Challenge,
+    rom: Arc<Rom>,
+    stats: Arc<HashStats>,
+    workers: usize,
+) -> SlotMining {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let deadline = chrono::DateTime::parse_from_rfc3339(&ch.latest_submission)
+        .ok()
+        .map(|d| d.with_timezone(&chrono::Utc));
+
+    let mut threads = Vec::with_capacity(workers);
+
+    for worker_id in 0..workers {
+        let slot_idx = worker_id % slots.len();
+        let slots = slots.clone();
+        let rom = rom.clone();
+        let stats = stats.clone();
+        let stop = stop.clone();
+        let tx = tx.clone();
+        let ch = ch.clone();
+
+        threads.push(std::thread::spawn(move || {
+            let slot = &slots[slot_idx];
+
+            // Per-thread deterministic RNG seed
+            let mut seed = [0u8; 32];
+            seed[..8].copy_from_slice(&(worker_id as u64).to_le_bytes());
+            let mut rng = ChaCha12Rng::from_seed(seed);
+
+            let (mut generation, mut address) = slot.load();
+
+            const BATCH: usize = 256;
+            loop {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(dead) = deadline {
+                    if chrono::Utc::now() > dead {
+                        return;
+                    }
+                }
+                if slot.generation.load(Ordering::Acquire) != generation {
+                    (generation, address) = slot.load();
+                }
+
+                let Some(addr) = address.as_deref() else {
+                    // Parked until the next address is registered
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    continue;
+                };
+
+                let mut tried = BATCH as u64;
+                for i in 0..BATCH {
+                    let mut nonce = [0u8; 8];
+                    rng.fill_bytes(&mut nonce);
+                    let nonce_hex = hex::encode(nonce);
+
+                    let preimage = build_preimage(
+                        &nonce_hex,
+                        addr,
+                        &ch.challenge_id,
+                        &ch.difficulty,
+                        &ch.no_pre_mine,
+                        &ch.latest_submission,
+                        &ch.no_pre_mine_hour,
+                    );
+
+                    let digest = ash_hash(preimage.as_bytes(), &rom);
+
+                    if matches_diff(&digest, &ch.difficulty) {
+                        tried = i as u64 + 1;
+                        if slot.claim(generation) {
+                            let _ = tx.send((slot_idx, nonce_hex));
+                        }
+                        break;
+                    }
+                }
+                stats.add(worker_id, tried);
+            }
+        }));
+    }
+
+    SlotMining { solutions: rx, stop, threads }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::PrivateKey;
+
+    fn bundle(address: &str) -> AddressBundle {
+        AddressBundle {
+            address: address.into(),
+            pubkey: [0; 32],
+            privkey: PrivateKey::from_bytes(&[7; 32]).unwrap(),
+            address_raw: Vec::new(),
+            stake_key_hash: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_returns_undelivered_solutions() {
+        let ch = Challenge {
+            challenge_id: "**D01C01".into(),
+            day: 1,
+            challenge_number: 1,
+            issued_at: String::new(),
+            latest_submission: String::new(),
+            // Every hash solves it
+            difficulty: "FFFFFFFF".into(),
+            no_pre_mine: "seed".into(),
+            no_pre_mine_hour: String::new(),
+        };
+        let rom = Arc::new(super::super::rom::build_rom(&ch.no_pre_mine));
+        let stats = Arc::new(HashStats::new(2));
+        stats.start_challenge(&ch);
+
+        let slots = Arc::new(vec![AddressSlot::new(), AddressSlot::new()]);
+        slots[0].set(Some(&bundle("addr_a")));
+        slots[1].set(Some(&bundle("addr_b")));
+        let running = mine_addresses(slots.clone(), &ch, rom, stats.clone(), 2);
+
+        // A slot parks when it claims; the send follows before its thread exits
+        while slots.iter().any(|s| s.load().1.is_some()) {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let mut late = running.stop().await;
+        late.sort();
+
+        assert_eq!(late.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 1]);
+        assert!(late.iter().all(|(_, nonce)| nonce.len() == 16));
+    }
+}
@@ -0,0 +1,552 @@
+//! Local stand-in for the Scavenger API, for dry runs and end-to-end tests
+//! without touching production. Run with `scavenger-miner mock-server` and
+//! point the miner at it with `--api http://127.0.0.1:8080`. Tests start one
+//! in-process with `spawn`.
+
+use anyhow::Result;
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use bech32::FromBase32;
+use ed25519_dalek::{Signer, SigningKey};
+use rand::Rng;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::api::types::{Challenge, TandCResponse};
+use crate::mining::rom::RomCache;
+use crate::mining::worker::{ash_hash, build_preimage, matches_diff};
+use crate::util::bech::blake2b224;
+use crate::util::cip8::{decode_cose_key, decode_cose_sign1};
+
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// 8 hex chars; every zero bit must be zero in the hash (fewer = easier)
+    pub difficulty: String,
+    /// Seconds between new challenges
+    pub rotate_secs: u64,
+    /// Seconds a challenge accepts submissions after it is issued
+    pub window_secs: u64,
+    pub challenges_per_day: u32,
+    /// Length of the mining period (`max_day`); `/challenge` answers
+    /// "after" once it is over
+    pub days: u32,
+    /// STAR per receipt for every day
+    pub star_rate: u64,
+    /// Fraction of requests answered with an injected 429/5xx
+    pub error_rate: f64,
+}
+
+struct MockState {
+    cfg: MockConfig,
+    tandc: TandCResponse,
+    started: chrono::DateTime<chrono::Utc>,
+    server_key: SigningKey,
+    roms: RomCache,
+    /// address => registered pubkey
+    registered: Mutex<HashMap<String, [u8; 32]>>,
+    /// (address, challenge_id)
+    solved: Mutex<HashSet<(String, String)>>,
+    /// source => target
+    donations: Mutex<HashMap<String, String>>,
+}
+
+type Shared = Arc<MockState>;
+
+/// Error body in the same shape the real backend uses.
+struct MockError(StatusCode, String);
+
+impl IntoResponse for MockError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "message": self.1,
+            "error": self.0.canonical_reason().unwrap_or(""),
+            "statusCode": self.0.as_u16(),
+        });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+fn bad_request(msg: impl Into<String>) -> MockError {
+    MockError(StatusCode::BAD_REQUEST, msg.into())
+}
+
+type MockResult = std::result::Result<Json<Value>, MockError>;
+
+impl MockState {
+    fn new(cfg: MockConfig, roms: RomCache) -> Self {
+        Self {
+            cfg,
+            tandc: TandCResponse {
+                version: "1-0".into(),
+                content: "Mock terms and conditions.".into(),
+                message: "I agree to abide by the terms and conditions as described in version 1-0 of the Midnight scavenger mining process".into(),
+            },
+            started: chrono::Utc::now(),
+            server_key: SigningKey::generate(&mut rand::rngs::OsRng),
+            roms,
+            registered: Mutex::new(HashMap::new()),
+            solved: Mutex::new(HashSet::new()),
+            donations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn total_challenges(&self) -> u64 {
+        self.cfg.days.max(1) as u64 * self.cfg.challenges_per_day.max(1) as u64
+    }
+
+    fn challenge_index_now(&self) -> u64 {
+        let elapsed = (chrono::Utc::now() - self.started).num_seconds().max(0) as u64;
+        elapsed / self.cfg.rotate_secs.max(1)
+    }
+
+    /// The `n`-th challenge since start (0-based).
+    fn challenge_at(&self, n: u64) -> Challenge {
+        let per_day = self.cfg.challenges_per_day.max(1) as u64;
+        let day = (n / per_day + 1) as u32;
+        let number = (n % per_day + 1) as u32;
+
+        let issued = self.started + chrono::Duration::seconds((n * self.cfg.rotate_secs) as i64);
+        let latest = issued + chrono::Duration::seconds(self.cfg.window_secs as i64);
+        let fmt = |t: chrono::DateTime<chrono::Utc>| {
+            t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+        };
+
+        Challenge {
+            challenge_id: format!("**D{:02}C{:02}", day, number),
+            day,
+            challenge_number: number,
+            issued_at: fmt(issued),
+            latest_submission: fmt(latest),
+            difficulty: self.cfg.difficulty.clone(),
+            // Changes once per day, like the real backend
+            no_pre_mine: hex::encode(blake2b224(format!("mock-day-{day}").as_bytes())),
+            no_pre_mine_hour: format!("{}", issued.timestamp() / 3600),
+        }
+    }
+
+    /// Look up an issued challenge by id (`**DddCnn`).
+    fn find_challenge(&self, id: &str) -> Option<Challenge> {
+        let rest = id.strip_prefix("**D")?;
+        let (day, number) = rest.split_once('C')?;
+        let (day, number): (u64, u64) = (day.parse().ok()?, number.parse().ok()?);
+        if day == 0 || number == 0 || number > self.cfg.challenges_per_day as u64 {
+            return None;
+        }
+        let n = (day - 1) * self.cfg.challenges_per_day as u64 + (number - 1);
+        (n <= self.challenge_index_now()).then(|| self.challenge_at(n))
+    }
+
+    fn sign_hex(&self, msg: &str) -> String {
+        hex::encode(self.server_key.sign(msg.as_bytes()).to_bytes())
+    }
+}
+
+fn decode_address(address: &str) -> std::result::Result<Vec<u8>, MockError> {
+    let (_hrp, data, _variant) =
+        bech32::decode(address).map_err(|e| bad_request(format!("Invalid address: {e}")))?;
+    Vec::<u8>::from_base32(&data).map_err(|e| bad_request(format!("Invalid address: {e}")))
+}
+
+async fn tandc(State(st): State<Shared>) -> Json<TandCResponse> {
+    Json(st.tandc.clone())
+}
+
+async fn tandc_version(State(st): State<Shared>, Path(_version): Path<String>) -> Json<TandCResponse> {
+    Json(st.tandc.clone())
+}
+
+async fn register(
+    State(st): State<Shared>,
+    Path((address, sig_hex, pubkey_hex)): Path<(String, String, String)>,
+) -> MockResult {
+    let raw = decode_address(&address)?;
+    // Raw 32-byte key, or a CIP-30 COSE_Key
+    let pubkey: [u8; 32] = hex::decode(&pubkey_hex)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b.as_slice()).ok().or_else(|| decode_cose_key(&b).ok()))
+        .ok_or_else(|| bad_request("Invalid public key"))?;
+
+    if raw.len() < 29 || raw[1..29] != blake2b224(&pubkey) {
+        return Err(bad_request("Public key does not match address"));
+    }
+
+    let cose = hex::decode(&sig_hex).map_err(|_| bad_request("Invalid signature encoding"))?;
+    let sign1 = decode_cose_sign1(&cose).map_err(|e| bad_request(format!("Invalid signature: {e}")))?;
+    if sign1.unprotected_bytes("address") != Some(raw.as_slice()) {
+        return Err(bad_request("Signature address header does not match address"));
+    }
+    if !sign1.payload_matches(st.tandc.message.trim_end().as_bytes()) {
+        return Err(bad_request("Signed message does not match the terms and conditions"));
+    }
+    sign1
+        .verify(&pubkey)
+        .map_err(|e| bad_request(format!("Invalid signature: {e}")))?;
+
+    st.registered.lock().unwrap().insert(address.clone(), pubkey);
+
+    let preimage = format!("{}{}", address, pubkey_hex);
+    Ok(Json(json!({
+        "registrationReceipt": {
+            "signature": st.sign_hex(&preimage),
+            "preimage": preimage,
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        }
+    })))
+}
+
+async fn challenge(State(st): State<Shared>) -> Json<Value> {
+    let n = st.challenge_index_now();
+    let total = st.total_challenges();
+    let period_ends = st.challenge_at(total).issued_at;
+
+    if n >= total {
+        return Json(json!({
+            "code": "after",
+            "max_day": st.cfg.days.max(1),
+            "total_challenges": total,
+            "mining_period_ends": period_ends,
+        }));
+    }
+
+    let ch = st.challenge_at(n);
+    let next = st.challenge_at(n + 1);
+    Json(json!({
+        "code": "active",
+        "challenge": ch,
+        "current_day": ch.day,
+        "max_day": st.cfg.days.max(1),
+        "total_challenges": total,
+        "mining_period_ends": period_ends,
+        "next_challenge_starts_at": next.issued_at,
+    }))
+}
+
+async fn solution(
+    State(st): State<Shared>,
+    Path((address, challenge_id, nonce)): Path<(String, String, String)>,
+) -> MockResult {
+    if !st.registered.lock().unwrap().contains_key(&address) {
+        return Err(bad_request("Address is not registered"));
+    }
+
+    let ch = st
+        .find_challenge(&challenge_id)
+        .ok_or_else(|| MockError(StatusCode::NOT_FOUND, "Challenge not found".into()))?;
+
+    let key = (address.clone(), challenge_id.clone());
+    if st.solved.lock().unwrap().contains(&key) {
+        return Err(bad_request("Solution already exists"));
+    }
+
+    let deadline = chrono::DateTime::parse_from_rfc3339(&ch.latest_submission)
+        .map_err(|e| MockError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if chrono::Utc::now() > deadline {
+        return Err(bad_request("Challenge expired"));
+    }
+
+    if nonce.len() != 16 || hex::decode(&nonce).is_err() {
+        return Err(bad_request("Invalid nonce"));
+    }
+
+    let preimage = build_preimage(
+        &nonce,
+        &address,
+        &ch.challenge_id,
+        &ch.difficulty,
+        &ch.no_pre_mine,
+        &ch.latest_submission,
+        &ch.no_pre_mine_hour,
+    );
+    let rom = st
+        .roms
+        .get(&ch.no_pre_mine)
+        .await
+        .map_err(|e| MockError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let digest = ash_hash(preimage.as_bytes(), &rom);
+    if !matches_diff(&digest, &ch.difficulty) {
+        return Err(bad_request("Invalid nonce: hash does not meet difficulty"));
+    }
+
+    // Re-check after the (slow) hash so concurrent submissions can't both win
+    if !st.solved.lock().unwrap().insert(key) {
+        return Err(bad_request("Solution already exists"));
+    }
+
+    tracing::info!("Accepted solution {} for {} from {}", nonce, challenge_id, address);
+    Ok(Json(json!({
+        "crypto_receipt": {
+            "signature": st.sign_hex(&preimage),
+            "preimage": preimage,
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        }
+    })))
+}
+
+async fn work_to_star_rate(State(st): State<Shared>) -> Json<Vec<u64>> {
+    // Rates are published for completed days only
+    let current_day = st.challenge_at(st.challenge_index_now()).day;
+    Json(vec![st.cfg.star_rate; current_day.saturating_sub(1) as usize])
+}
+
+async fn donate_to(
+    State(st): State<Shared>,
+    Path((dest, src, sig_hex)): Path<(String, String, String)>,
+) -> std::result::Result<String, MockError> {
+    let registered = st.registered.lock().unwrap().clone();
+    let pubkey = registered
+        .get(&src)
+        .ok_or_else(|| bad_request("Source address is not registered"))?;
+    if !registered.contains_key(&dest) {
+        return Err(bad_request("Destination address is not registered"));
+    }
+
+    let cose = hex::decode(&sig_hex).map_err(|_| bad_request("Invalid signature encoding"))?;
+    let sign1 = decode_cose_sign1(&cose).map_err(|e| bad_request(format!("Invalid signature: {e}")))?;
+    let expected = format!("Assign accumulated Scavenger rights to: {}", dest);
+    if !sign1.payload_matches(expected.as_bytes()) {
+        return Err(bad_request("Signed message does not match destination"));
+    }
+    sign1
+        .verify(pubkey)
+        .map_err(|e| bad_request(format!("Invalid signature: {e}")))?;
+
+    let mut donations = st.donations.lock().unwrap();
+    if donations.contains_key(&src) {
+        return Err(MockError(StatusCode::FORBIDDEN, "Source address already donated".into()));
+    }
+    if donations.contains_key(&dest) {
+        return Err(MockError(StatusCode::FORBIDDEN, "Destination address has donated".into()));
+    }
+    donations.insert(src.clone(), dest.clone());
+
+    Ok(format!("Donated {} to {}", src, dest))
+}
+
+/// Randomly answer with a 429 or 5xx instead of the real handler.
+async fn inject_errors(State(st): State<Shared>, req: Request, next: Next) -> Response {
+    if st.cfg.error_rate > 0.0 && rand::thread_rng().gen_bool(st.cfg.error_rate.min(1.0)) {
+        let statuses = [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ];
+        let status = statuses[rand::thread_rng().gen_range(0..statuses.len())];
+        tracing::info!("Injecting {} for {}", status, req.uri());
+        let mut resp = MockError(status, "injected failure".into()).into_response();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            resp.headers_mut().insert("retry-after", "1".parse().unwrap());
+        }
+        return resp;
+    }
+    next.run(req).await
+}
+
+fn router(state: Shared) -> Router {
+    Router::new()
+        .route("/TandC", get(tandc))
+        .route("/TandC/:version", get(tandc_version))
+        .route("/register/:address/:signature/:pubkey", post(register))
+        .route("/challenge", get(challenge))
+        .route("/solution/:address/:challenge_id/:nonce", post(solution))
+        .route("/work_to_star_rate", get(work_to_star_rate))
+        .route("/donate_to/:dest/:src/:signature", post(donate_to))
+        .layer(middleware::from_fn_with_state(state.clone(), inject_errors))
+        .with_state(state)
+}
+
+pub async fn serve(listen: &str, cfg: MockConfig) -> Result<()> {
+    let state: Shared = Arc::new(MockState::new(cfg, RomCache::new()));
+    tracing::info!(
+        "Mock server key (verifies receipts): {}",
+        hex::encode(state.server_key.verifying_key().to_bytes())
+    );
+    tracing::info!(cfg = ?state.cfg, "Mock Scavenger API listening on {}", listen);
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+/// Serve `state` on a free local port in the background.
+#[cfg(test)]
+async fn start(state: Shared) -> Result<std::net::SocketAddr> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router(state)).await {
+            tracing::error!("Mock server stopped: {e}");
+        }
+    });
+    Ok(addr)
+}
+
+/// Start a mock server in the background (for tests); point a
+/// `ScavengerClient` at `http://<addr>`.
+#[cfg(test)]
+pub async fn spawn(cfg: MockConfig) -> Result<std::net::SocketAddr> {
+    start(Arc::new(MockState::new(cfg, RomCache::new()))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounting::Accounting;
+    use crate::address::prefill::PrefillProvider;
+    use crate::address::shelley::ShelleyProvider;
+    use crate::address::AddressProvider;
+    use crate::api::ScavengerClient;
+    use crate::mining::{Miner, RegisterKey};
+    use crate::shutdown::Shutdown;
+    use crate::util::cip8::{cose_key_ed25519, cose_sign1_ed25519_with_headers};
+    use crate::Network;
+    use std::time::Duration;
+
+    fn easy() -> MockConfig {
+        MockConfig {
+            difficulty: "0FFFFFFF".into(),
+            rotate_secs: 3600,
+            window_secs: 3600,
+            challenges_per_day: 24,
+            days: 21,
+            star_rate: 10_000_000,
+            error_rate: 0.0,
+        }
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("scavenger-{name}-{}-{nanos}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn until(what: &str, mut done: impl FnMut() -> bool) {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(300);
+        while !done() {
+            assert!(tokio::time::Instant::now() < deadline, "timed out waiting for {what}");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn challenge_reports_mining_period() {
+        let addr = spawn(easy()).await.unwrap();
+        let client = ScavengerClient::new(format!("http://{addr}")).unwrap();
+
+        let env = client.get_challenge().await.unwrap();
+        assert_eq!(env.code, "active");
+        assert_eq!(env.current_day, Some(1));
+        assert_eq!(env.max_day, Some(21));
+        assert_eq!(env.total_challenges, Some(21 * 24));
+        assert_eq!(env.challenge.unwrap().challenge_id, "**D01C01");
+    }
+
+    #[tokio::test]
+    async fn register_accepts_raw_key_and_cose_key() {
+        let addr = spawn(easy()).await.unwrap();
+        let client = ScavengerClient::new(format!("http://{addr}")).unwrap();
+        let tandc = client.get_tandc(None).await.unwrap();
+
+        let dir = temp_dir("mock-register");
+        let provider = ShelleyProvider::new(Network::Preprod, dir.to_str().unwrap(), None).await.unwrap();
+        for key in [RegisterKey::Raw, RegisterKey::CoseKey] {
+            let a = provider.new_address().unwrap();
+            let cose = cose_sign1_ed25519_with_headers(&a.privkey, tandc.message.trim_end(), &a.address_raw, false);
+            let key_hex = match key {
+                RegisterKey::Raw => hex::encode(a.pubkey),
+                RegisterKey::CoseKey => hex::encode(cose_key_ed25519(&a.pubkey)),
+            };
+            let receipt = client.register(&a.address, &hex::encode(cose), &key_hex).await.unwrap();
+            assert_eq!(receipt.registrationReceipt.preimage, format!("{}{}", a.address, key_hex));
+        }
+    }
+
+    #[tokio::test]
+    async fn miner_registers_solves_and_donates() {
+        let roms = RomCache::new();
+        let state: Shared = Arc::new(MockState::new(easy(), roms.clone()));
+        let addr = start(state.clone()).await.unwrap();
+        let client = ScavengerClient::new(format!("http://{addr}")).unwrap();
+        let tandc = client.get_tandc(None).await.unwrap();
+
+        // Donation target: registered, but outside the miner's keystore
+        let dest_dir = temp_dir("mock-dest");
+        let dest = ShelleyProvider::new(Network::Preprod, dest_dir.to_str().unwrap(), None)
+            .await
+            .unwrap()
+            .new_address()
+            .unwrap();
+        let cose = cose_sign1_ed25519_with_headers(
+            &dest.privkey,
+            tandc.message.trim_end(),
+            &dest.address_raw,
+            false,
+        );
+        client
+            .register(&dest.address, &hex::encode(cose), &hex::encode(dest.pubkey))
+            .await
+            .unwrap();
+
+        let keystore = temp_dir("mock-miner");
+        let keystore_str = keystore.to_str().unwrap().to_string();
+        let shelley = ShelleyProvider::new(Network::Preprod, &keystore_str, None).await.unwrap();
+        let provider = PrefillProvider::new(shelley, &keystore_str, None).unwrap();
+
+        let shutdown = Shutdown::new();
+        let miner = Miner::new(
+            client,
+            provider,
+            &keystore_str,
+            Some(2),
+            1,
+            Network::Preprod,
+            true,
+            Some(dest.address.clone()),
+        )
+        .with_shutdown(shutdown.clone())
+        .with_challenge_poll(Duration::from_secs(1))
+        .with_roms(roms)
+        // The mock checks hashed payloads too; exercise that path here
+        .with_cip8_hashed(true);
+        let stop_after_donation = async {
+            until("a donation", || !state.donations.lock().unwrap().is_empty()).await;
+            shutdown.trigger();
+        };
+        let (result, ()) = tokio::join!(miner.run_loop(tandc), stop_after_donation);
+        result.unwrap();
+
+        let (source, target) = state
+            .donations
+            .lock()
+            .unwrap()
+            .iter()
+            .next()
+            .map(|(s, t)| (s.clone(), t.clone()))
+            .unwrap();
+        assert_eq!(target, dest.address);
+        assert!(state.solved.lock().unwrap().contains(&(source.clone(), "**D01C01".to_string())));
+
+        let accounting = Accounting::new(&keystore).unwrap();
+        let registration = accounting.registration(&source).unwrap().expect("registration recorded");
+        assert!(registration.receipt.is_some());
+
+        let receipts = accounting.read_all_receipts().unwrap();
+        let receipt = receipts.iter().find(|r| r.address == source).expect("receipt stored");
+        assert_eq!(receipt.challenge_id, "**D01C01");
+        let crypto = receipt.crypto_receipt.as_ref().expect("crypto receipt stored");
+        let nonce = receipt.nonce.as_deref().expect("nonce stored");
+        assert!(crypto.preimage.starts_with(&format!("{nonce}{source}")));
+
+        let _ = std::fs::remove_dir_all(&keystore);
+        let _ = std::fs::remove_dir_all(&dest_dir);
+    }
+}
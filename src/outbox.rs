@@ -0,0 +1,159 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::api::types::Challenge;
+
+/// A found nonce that has to reach the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoundSolution {
+    pub address: String,
+    pub challenge_id: String,
+    pub nonce: String,
+    pub day: u32,
+    pub challenge_number: u32,
+    /// Deadline of the challenge; the solution is worthless afterwards.
+    pub latest_submission: String,
+}
+
+impl FoundSolution {
+    pub fn new(ch: &Challenge, address: &str, nonce: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            challenge_id: ch.challenge_id.clone(),
+            nonce: nonce.to_string(),
+            day: ch.day,
+            challenge_number: ch.challenge_number,
+            latest_submission: ch.latest_submission.clone(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        chrono::DateTime::parse_from_rfc3339(&self.latest_submission)
+            .map(|d| d.with_timezone(&chrono::Utc) < chrono::Utc::now())
+            .unwrap_or(false)
+    }
+
+    fn key(&self) -> (String, String, String) {
+        (self.address.clone(), self.challenge_id.clone(), self.nonce.clone())
+    }
+}
+
+/// Where a found solution stands.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SolutionStatus {
+    /// Written before the first submission attempt; retried until final.
+    Pending,
+    /// Written right before the first `/solution` request. From then on the
+    /// server may hold the solution even if we never saw its answer, so a
+    /// replay answered with "already solved" means it was accepted.
+    Submitted,
+    Accepted { timestamp: String },
+    Rejected { reason: String },
+    /// The challenge deadline passed before the server accepted it.
+    Expired,
+}
+
+impl SolutionStatus {
+    /// Not final yet: the outbox replays it.
+    pub fn is_unsettled(&self) -> bool {
+        matches!(self, SolutionStatus::Pending | SolutionStatus::Submitted)
+    }
+}
+
+/// One JSONL line: a solution and its status at `recorded_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxRecord {
+    #[serde(flatten)]
+    pub solution: FoundSolution,
+    #[serde(flatten)]
+    pub status: SolutionStatus,
+    pub recorded_at: String,
+}
+
+/// Append-only log of found solutions and their final status:
+///   keystore/00outbox.jsonl
+///
+/// The last line for a solution wins, so a solution whose last status is
+/// `pending` or `submitted` has not been settled yet (e.g. the process died
+/// or the server was unreachable) and gets replayed.
+pub struct Outbox {
+    path: PathBuf,
+}
+
+impl Outbox {
+    /// Construct using env var KEYSTORE (defaults to "keystore")
+    pub fn new_from_env() -> Result<Self> {
+        let root = std::env::var("KEYSTORE").unwrap_or_else(|_| "keystore".to_string());
+        Self::new(root)
+    }
+
+    /// Construct from explicit keystore directory.
+    pub fn new<P: AsRef<Path>>(keystore_dir: P) -> Result<Self> {
+        let root = keystore_dir.as_ref();
+        fs::create_dir_all(root)?;
+        let path = root.join("00outbox.jsonl");
+        Ok(Self { path })
+    }
+
+    /// Append a status line for `solution` (flushed to disk before returning).
+    pub fn record(&self, solution: &FoundSolution, status: SolutionStatus) -> Result<()> {
+        let rec = OutboxRecord {
+            solution: solution.clone(),
+            status,
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(f, "{}", serde_json::to_string(&rec)?)?;
+        f.sync_data()?;
+        Ok(())
+    }
+
+    /// Read all outbox lines.
+    pub fn read_all(&self) -> Result<Vec<OutboxRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let f = OpenOptions::new().read(true).open(&self.path)?;
+        let reader = BufReader::new(f);
+        let mut out = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<OutboxRecord>(&line) {
+                Ok(rec) => out.push(rec),
+                Err(e) => {
+                    // tolerate a bad line; continue
+                    tracing::warn!("Ignoring malformed outbox line: {e}");
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Latest record of every solution not settled yet (`pending` or
+    /// `submitted`), oldest first.
+    pub fn pending(&self) -> Result<Vec<OutboxRecord>> {
+        let mut latest: HashMap<(String, String, String), (usize, OutboxRecord)> = HashMap::new();
+        for (i, rec) in self.read_all()?.into_iter().enumerate() {
+            let first_seen = latest.get(&rec.solution.key()).map(|(i, _)| *i).unwrap_or(i);
+            latest.insert(rec.solution.key(), (first_seen, rec));
+        }
+
+        let mut pending: Vec<(usize, OutboxRecord)> = latest
+            .into_values()
+            .filter(|(_, r)| r.status.is_unsettled())
+            .collect();
+        pending.sort_by_key(|(i, _)| *i);
+        Ok(pending.into_iter().map(|(_, s)| s).collect())
+    }
+}
@@ -0,0 +1,388 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Timelike, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::accounting::{ReceiptRecord, STAR_PER_NIGHT};
+use crate::api::types::Challenge;
+use crate::mining::telemetry::expected_hashes;
+
+/// Days of history the STAR rate, hashrate and difficulty trend look at.
+const WINDOW_DAYS: u32 = 7;
+/// Two-sided 90% quantile of the normal distribution.
+const Z_90: f64 = 1.645;
+const SECS_PER_DAY: f64 = 86_400.0;
+
+/// What the projection is computed from.
+pub struct ProjectionInput<'a> {
+    pub receipts: &'a [ReceiptRecord],
+    /// STAR per receipt, index 0 => day 1 (published for completed days)
+    pub star_rates: &'a [u64],
+    pub challenges: &'a [Challenge],
+    /// `ChallengeEnvelope::current_day` / `max_day`
+    pub current_day: u32,
+    pub max_day: u32,
+    /// Difficulty being mined now (used when there is no challenge history)
+    pub current_difficulty: Option<&'a str>,
+    /// Live hashrate (H/s); otherwise the effective rate is measured from
+    /// the receipts of the last `WINDOW_DAYS` days
+    pub hashrate: Option<f64>,
+    pub now: DateTime<Utc>,
+}
+
+/// Expected NIGHT by the end of the mining period.
+#[derive(Debug, Serialize)]
+pub struct Projection {
+    pub current_day: u32,
+    pub max_day: u32,
+    /// Days left including the rest of today
+    pub remaining_days: f64,
+    pub hashrate: f64,
+    /// "live" or "receipts"
+    pub hashrate_source: &'static str,
+    /// Mean and standard deviation of the recent STAR rates
+    pub star_rate_mean: f64,
+    pub star_rate_sd: f64,
+    /// Difficulty as zero bits in the mask (log2 of hashes per solution) today,
+    /// and its trend per day
+    pub difficulty_bits: f64,
+    pub difficulty_bits_per_day: f64,
+    /// NIGHT from days whose STAR rate is published
+    pub earned_night: f64,
+    /// Receipts on days without a published rate yet, valued at the mean rate
+    pub pending_solutions: u64,
+    pub pending_night: f64,
+    pub future_solutions: f64,
+    pub future_night: f64,
+    /// Earned + pending + future, with a `confidence` interval
+    pub expected_night: f64,
+    pub low_night: f64,
+    pub high_night: f64,
+    pub confidence: f64,
+}
+
+/// Project NIGHT by `max_day`.
+///
+/// Future solutions per day are `hashrate / 2^bits` with the difficulty
+/// following its recent linear trend, counted as Poisson; each future day's
+/// STAR rate is drawn from the mean and spread of the recent rates. The
+/// interval also covers the sampling error of a hashrate measured from
+/// receipts, but not changes in how long the miner runs.
+pub fn project(input: &ProjectionInput) -> Result<Projection> {
+    let window_start = input.current_day.saturating_sub(WINDOW_DAYS);
+
+    // STAR rates
+    let recent: Vec<f64> = input
+        .star_rates
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i as u32 + 1 > window_start)
+        .map(|(_, r)| *r as f64)
+        .collect();
+    let recent = if recent.is_empty() {
+        // Nothing published lately; fall back to the last known rate
+        input.star_rates.last().map(|r| vec![*r as f64]).unwrap_or_default()
+    } else {
+        recent
+    };
+    if recent.is_empty() {
+        bail!("no STAR rates published yet");
+    }
+    let (rate_mean, rate_sd) = mean_sd(&recent);
+
+    // Difficulty trend
+    let difficulty_of: HashMap<&str, &str> = input
+        .challenges
+        .iter()
+        .map(|c| (c.challenge_id.as_str(), c.difficulty.as_str()))
+        .collect();
+    let mut bits_by_day: BTreeMap<u32, Vec<f64>> = BTreeMap::new();
+    for c in input.challenges.iter().filter(|c| c.day > window_start) {
+        bits_by_day.entry(c.day).or_default().push(expected_hashes(&c.difficulty).log2());
+    }
+    let points: Vec<(f64, f64)> = bits_by_day
+        .iter()
+        .map(|(day, bits)| (*day as f64, mean_sd(bits).0))
+        .collect();
+    let (bits_now, bits_slope) = match (points.len(), input.current_difficulty) {
+        (0, Some(d)) => (expected_hashes(d).log2(), 0.0),
+        (0, None) => bail!("no challenge difficulty known"),
+        (1, _) => (points[0].1, 0.0),
+        _ => {
+            let (intercept, slope) = linear_fit(&points);
+            (intercept + slope * input.current_day as f64, slope)
+        }
+    };
+
+    // Hashrate
+    let day_left = 1.0 - input.now.num_seconds_from_midnight() as f64 / SECS_PER_DAY;
+    let (hashrate, source, samples) = match input.hashrate {
+        Some(h) => (h, "live", None),
+        None => {
+            let since = input.now - chrono::Duration::days(WINDOW_DAYS as i64);
+            let mut work = 0.0;
+            let mut n = 0u64;
+            let mut first = input.now;
+            for r in input.receipts {
+                let Ok(t) = DateTime::parse_from_rfc3339(&r.timestamp) else { continue };
+                let t = t.with_timezone(&Utc);
+                let Some(diff) = difficulty_of.get(r.challenge_id.as_str()).copied().or(input.current_difficulty)
+                else {
+                    continue;
+                };
+                if t >= since {
+                    work += expected_hashes(diff);
+                    n += 1;
+                    first = first.min(t);
+                }
+            }
+            if n == 0 {
+                bail!("no receipts in the last {} days to measure the hashrate from", WINDOW_DAYS);
+            }
+            let span = (input.now - first).num_seconds().max(3600) as f64;
+            (work / span, "receipts", Some(n))
+        }
+    };
+
+    // Earned so far
+    let mut earned_star = 0.0;
+    let mut pending = 0u64;
+    for r in input.receipts {
+        match r.day.checked_sub(1).and_then(|i| input.star_rates.get(i as usize)) {
+            Some(rate) => earned_star += *rate as f64,
+            None => pending += 1,
+        }
+    }
+    let pending_star = pending as f64 * rate_mean;
+    let pending_var = (pending as f64 * rate_sd).powi(2);
+
+    // Future days
+    let mut future_solutions = 0.0;
+    let mut poisson_var = 0.0;
+    let mut rate_var = 0.0;
+    let mut remaining_days = 0.0;
+    for day in input.current_day.max(1)..=input.max_day {
+        let share = if day == input.current_day { day_left } else { 1.0 };
+        let bits = (bits_now + bits_slope * (day as f64 - input.current_day as f64)).clamp(0.0, 32.0);
+        let solutions = hashrate * SECS_PER_DAY * share / bits.exp2();
+        future_solutions += solutions;
+        poisson_var += solutions * (rate_mean.powi(2) + rate_sd.powi(2));
+        rate_var += (solutions * rate_sd).powi(2);
+        remaining_days += share;
+    }
+    let future_star = future_solutions * rate_mean;
+    // Relative error of a rate measured from n solutions is 1/sqrt(n)
+    let hashrate_var = samples.map(|n| future_star.powi(2) / n as f64).unwrap_or(0.0);
+
+    let expected_star = earned_star + pending_star + future_star;
+    let sd_star = (pending_var + poisson_var + rate_var + hashrate_var).sqrt();
+    let night = |star: f64| star / STAR_PER_NIGHT;
+
+    Ok(Projection {
+        current_day: input.current_day,
+        max_day: input.max_day,
+        remaining_days,
+        hashrate,
+        hashrate_source: source,
+        star_rate_mean: rate_mean,
+        star_rate_sd: rate_sd,
+        difficulty_bits: bits_now,
+        difficulty_bits_per_day: bits_slope,
+        earned_night: night(earned_star),
+        pending_solutions: pending,
+        pending_night: night(pending_star),
+        future_solutions,
+        future_night: night(future_star),
+        expected_night: night(expected_star),
+        low_night: night((expected_star - Z_90 * sd_star).max(earned_star)),
+        high_night: night(expected_star + Z_90 * sd_star),
+        confidence: 0.9,
+    })
+}
+
+fn mean_sd(xs: &[f64]) -> (f64, f64) {
+    let n = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+    if xs.len() < 2 {
+        return (mean, 0.0);
+    }
+    let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, var.sqrt())
+}
+
+/// Least-squares line through `points`: (intercept, slope).
+fn linear_fit(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mx = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let my = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mx).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mx) * (p.1 - my)).sum();
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    (my - slope * mx, slope)
+}
+
+impl Projection {
+    /// One line for the periodic miner log.
+    pub fn log(&self) {
+        tracing::info!(
+            "Projection — NIGHT by day {}: {:.2} ({:.0}%: {:.2}–{:.2}) — earned {:.2}, {:.1} solutions/day at {:.1} H/s ({}), {:.1} days left",
+            self.max_day,
+            self.expected_night,
+            self.confidence * 100.0,
+            self.low_night,
+            self.high_night,
+            self.earned_night,
+            self.future_solutions / self.remaining_days.max(f64::EPSILON),
+            self.hashrate,
+            self.hashrate_source,
+            self.remaining_days
+        );
+    }
+
+    pub fn print_table(&self) {
+        println!("Day {} of {} ({:.1} days left)", self.current_day, self.max_day, self.remaining_days);
+        println!("Hashrate:        {:.1} H/s ({})", self.hashrate, self.hashrate_source);
+        println!(
+            "Difficulty:      {:.1} bits ({:+.2} bits/day)",
+            self.difficulty_bits, self.difficulty_bits_per_day
+        );
+        println!("STAR rate:       {:.0} ± {:.0} per solution", self.star_rate_mean, self.star_rate_sd);
+        println!();
+        println!("Earned:          {:>14.6} NIGHT", self.earned_night);
+        println!(
+            "Pending:         {:>14.6} NIGHT ({} solutions, rate not published yet)",
+            self.pending_night, self.pending_solutions
+        );
+        println!(
+            "Future:          {:>14.6} NIGHT ({:.1} solutions)",
+            self.future_night, self.future_solutions
+        );
+        println!("Expected total:  {:>14.6} NIGHT", self.expected_night);
+        println!(
+            "{:.0}% interval:    {:>14.6} – {:.6} NIGHT",
+            self.confidence * 100.0,
+            self.low_night,
+            self.high_night
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(day: u32, number: u32, difficulty: &str) -> Challenge {
+        Challenge {
+            challenge_id: format!("**D{day:02}C{number:02}"),
+            day,
+            challenge_number: number,
+            issued_at: String::new(),
+            latest_submission: String::new(),
+            difficulty: difficulty.into(),
+            no_pre_mine: String::new(),
+            no_pre_mine_hour: String::new(),
+        }
+    }
+
+    fn receipt(day: u32) -> ReceiptRecord {
+        ReceiptRecord {
+            timestamp: format!("2025-11-{day:02}T06:00:00.000Z"),
+            address: "addr_a".into(),
+            challenge_id: format!("**D{day:02}C01"),
+            day,
+            challenge_number: 1,
+            nonce: None,
+            crypto_receipt: None,
+        }
+    }
+
+    /// Noon on `day` (day 1 = 2025-11-01), so half of today is left.
+    fn noon(day: u32) -> DateTime<Utc> {
+        format!("2025-11-{day:02}T12:00:00Z").parse().unwrap()
+    }
+
+    fn input<'a>(
+        receipts: &'a [ReceiptRecord],
+        star_rates: &'a [u64],
+        challenges: &'a [Challenge],
+        current_day: u32,
+    ) -> ProjectionInput<'a> {
+        ProjectionInput {
+            receipts,
+            star_rates,
+            challenges,
+            current_day,
+            max_day: current_day,
+            current_difficulty: None,
+            // 2 solutions/day at 16 zero bits
+            hashrate: Some(2.0 * 65536.0 / SECS_PER_DAY),
+            now: noon(current_day),
+        }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn splits_earned_pending_and_future() {
+        // Day 1 rate published, day 2 (today) not yet
+        let receipts = [receipt(1), receipt(1), receipt(2)];
+        let challenges = [challenge(2, 1, "0000FFFF")];
+        let p = project(&input(&receipts, &[2_000_000], &challenges, 2)).unwrap();
+
+        assert_close(p.earned_night, 4.0);
+        assert_eq!(p.pending_solutions, 1);
+        assert_close(p.pending_night, 2.0);
+        assert_close(p.remaining_days, 0.5);
+        assert_close(p.future_solutions, 1.0);
+        assert_close(p.future_night, 2.0);
+        assert_close(p.expected_night, 8.0);
+        assert!(p.low_night >= p.earned_night && p.high_night > p.expected_night);
+    }
+
+    #[test]
+    fn single_difficulty_point_has_no_trend() {
+        let challenges = [challenge(2, 1, "0000FFFF"), challenge(2, 2, "0000FFFF")];
+        let p = project(&input(&[], &[1_000_000], &challenges, 2)).unwrap();
+        assert_close(p.difficulty_bits, 16.0);
+        assert_close(p.difficulty_bits_per_day, 0.0);
+    }
+
+    #[test]
+    fn difficulty_follows_its_trend() {
+        let challenges = [challenge(1, 1, "0FFFFFFF"), challenge(2, 1, "00FFFFFF")];
+        let p = project(&input(&[], &[1_000_000], &challenges, 3)).unwrap();
+        assert_close(p.difficulty_bits_per_day, 4.0);
+        assert_close(p.difficulty_bits, 12.0);
+    }
+
+    #[test]
+    fn falls_back_to_last_rate_outside_the_window() {
+        // Nothing published in the last WINDOW_DAYS days
+        let challenges = [challenge(10, 1, "0000FFFF")];
+        let p = project(&input(&[], &[1_000_000, 3_000_000], &challenges, 10)).unwrap();
+        assert_close(p.star_rate_mean, 3_000_000.0);
+        assert_close(p.star_rate_sd, 0.0);
+    }
+
+    #[test]
+    fn needs_a_star_rate() {
+        let challenges = [challenge(1, 1, "0000FFFF")];
+        let err = project(&input(&[], &[], &challenges, 1)).unwrap_err();
+        assert!(err.to_string().contains("no STAR rates"), "{err}");
+    }
+
+    #[test]
+    fn mean_sd_and_linear_fit() {
+        let (mean, sd) = mean_sd(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_close(mean, 5.0);
+        assert_close(sd, (32.0f64 / 7.0).sqrt());
+        assert_eq!(mean_sd(&[3.0]), (3.0, 0.0));
+
+        let (intercept, slope) = linear_fit(&[(1.0, 1.0), (2.0, 3.0), (3.0, 5.0)]);
+        assert_close(intercept, -1.0);
+        assert_close(slope, 2.0);
+        assert_eq!(linear_fit(&[(4.0, 7.0), (4.0, 9.0)]), (8.0, 0.0));
+    }
+}
@@ -0,0 +1,360 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::accounting::{ReceiptRecord, STAR_PER_NIGHT};
+use crate::api::types::Challenge;
+use crate::donations::DonationRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Which breakdown to print (all of them if not given).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsBy {
+    Day,
+    Address,
+    Challenge,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Totals {
+    pub solutions: u64,
+    pub star: u128,
+    pub night: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DayStats {
+    pub day: u32,
+    pub solutions: u64,
+    /// STAR per receipt (not published until the day is over)
+    pub rate: Option<u64>,
+    pub star: u128,
+    pub night: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressStats {
+    pub address: String,
+    pub solutions: u64,
+    pub donated_to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChallengeStats {
+    pub challenge_id: String,
+    pub day: u32,
+    pub challenge_number: u32,
+    /// Difficulty mask, if the challenge is in the accounting store
+    pub difficulty: Option<String>,
+    /// Number of our addresses that solved it
+    pub solutions: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub totals: Totals,
+    pub days: Vec<DayStats>,
+    pub addresses: Vec<AddressStats>,
+    pub challenges: Vec<ChallengeStats>,
+}
+
+/// Break `receipts` down per day, address and challenge. `star_rates` is
+/// indexed by day - 1, as served by `/work_to_star_rate`.
+pub fn collect(
+    receipts: &[ReceiptRecord],
+    star_rates: &[u64],
+    donations: &[DonationRecord],
+    challenges: &[Challenge],
+) -> Stats {
+    let rate_of = |day: u32| day.checked_sub(1).and_then(|i| star_rates.get(i as usize)).copied();
+
+    let mut per_day: BTreeMap<u32, u64> = BTreeMap::new();
+    let mut per_address: BTreeMap<&str, u64> = BTreeMap::new();
+    let mut per_challenge: BTreeMap<(u32, u32, &str), u64> = BTreeMap::new();
+    for r in receipts {
+        *per_day.entry(r.day).or_default() += 1;
+        *per_address.entry(r.address.as_str()).or_default() += 1;
+        *per_challenge.entry((r.day, r.challenge_number, r.challenge_id.as_str())).or_default() += 1;
+    }
+
+    let days: Vec<DayStats> = per_day
+        .into_iter()
+        .map(|(day, solutions)| {
+            let rate = rate_of(day);
+            let star = rate.unwrap_or(0) as u128 * solutions as u128;
+            DayStats { day, solutions, rate, star, night: star as f64 / STAR_PER_NIGHT }
+        })
+        .collect();
+
+    let donated: HashMap<&str, &str> =
+        donations.iter().map(|d| (d.source.as_str(), d.target.as_str())).collect();
+    let addresses = per_address
+        .into_iter()
+        .map(|(address, solutions)| AddressStats {
+            address: address.to_string(),
+            solutions,
+            donated_to: donated.get(address).map(|t| t.to_string()),
+        })
+        .collect();
+
+    let known: HashMap<&str, &Challenge> =
+        challenges.iter().map(|c| (c.challenge_id.as_str(), c)).collect();
+    let challenges = per_challenge
+        .into_iter()
+        .map(|((day, challenge_number, id), solutions)| ChallengeStats {
+            challenge_id: id.to_string(),
+            day,
+            challenge_number,
+            difficulty: known.get(id).map(|c| c.difficulty.clone()),
+            solutions,
+        })
+        .collect();
+
+    let star = days.iter().map(|d| d.star).sum::<u128>();
+    Stats {
+        totals: Totals {
+            solutions: receipts.len() as u64,
+            star,
+            night: star as f64 / STAR_PER_NIGHT,
+        },
+        days,
+        addresses,
+        challenges,
+    }
+}
+
+/// Rows of one breakdown: header and cells, as strings.
+fn table_rows(stats: &Stats, by: StatsBy) -> (Vec<&'static str>, Vec<Vec<String>>) {
+    let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+    match by {
+        StatsBy::Day => (
+            vec!["day", "solutions", "rate", "star", "night"],
+            stats
+                .days
+                .iter()
+                .map(|d| {
+                    vec![
+                        d.day.to_string(),
+                        d.solutions.to_string(),
+                        d.rate.map(|r| r.to_string()).unwrap_or_default(),
+                        d.star.to_string(),
+                        format!("{:.6}", d.night),
+                    ]
+                })
+                .collect(),
+        ),
+        StatsBy::Address => (
+            vec!["address", "solutions", "donated_to"],
+            stats
+                .addresses
+                .iter()
+                .map(|a| vec![a.address.clone(), a.solutions.to_string(), opt(&a.donated_to)])
+                .collect(),
+        ),
+        StatsBy::Challenge => (
+            vec!["challenge_id", "day", "number", "difficulty", "solutions"],
+            stats
+                .challenges
+                .iter()
+                .map(|c| {
+                    vec![
+                        c.challenge_id.clone(),
+                        c.day.to_string(),
+                        c.challenge_number.to_string(),
+                        opt(&c.difficulty),
+                        c.solutions.to_string(),
+                    ]
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn title(by: StatsBy) -> &'static str {
+    match by {
+        StatsBy::Day => "Per day",
+        StatsBy::Address => "Per address",
+        StatsBy::Challenge => "Per challenge",
+    }
+}
+
+impl Stats {
+    pub fn print(&self, format: StatsFormat, by: Option<StatsBy>) -> anyhow::Result<()> {
+        let sections: Vec<StatsBy> = match by {
+            Some(by) => vec![by],
+            None => vec![StatsBy::Day, StatsBy::Address, StatsBy::Challenge],
+        };
+
+        match format {
+            StatsFormat::Json => match by {
+                None => println!("{}", serde_json::to_string_pretty(self)?),
+                Some(StatsBy::Day) => println!("{}", serde_json::to_string_pretty(&self.days)?),
+                Some(StatsBy::Address) => {
+                    println!("{}", serde_json::to_string_pretty(&self.addresses)?)
+                }
+                Some(StatsBy::Challenge) => {
+                    println!("{}", serde_json::to_string_pretty(&self.challenges)?)
+                }
+            },
+            StatsFormat::Csv => {
+                for (i, by) in sections.into_iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    let (header, rows) = table_rows(self, by);
+                    println!("{}", header.join(","));
+                    for row in rows {
+                        let cells: Vec<String> = row.iter().map(|c| csv_cell(c)).collect();
+                        println!("{}", cells.join(","));
+                    }
+                }
+            }
+            StatsFormat::Table => {
+                for by in sections {
+                    println!("{}", title(by));
+                    let (header, rows) = table_rows(self, by);
+                    print_table(&header, &rows);
+                    println!();
+                }
+                println!(
+                    "Total — solutions: {} — STAR: {} — NIGHT: {:.6}",
+                    self.totals.solutions, self.totals.star, self.totals.night
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Columns padded to their widest cell; numbers right-aligned.
+fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+    let numeric = |col: usize| {
+        rows.iter().all(|r| r[col].is_empty() || r[col].parse::<f64>().is_ok())
+    };
+    let line = |cells: Vec<&str>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                if numeric(i) {
+                    format!("{:>w$}", c, w = widths[i])
+                } else {
+                    format!("{:<w$}", c, w = widths[i])
+                }
+            })
+            .collect();
+        println!("  {}", cells.join("  ").trim_end());
+    };
+
+    line(header.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+fn csv_cell(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(address: &str, day: u32, number: u32) -> ReceiptRecord {
+        ReceiptRecord {
+            timestamp: "2025-11-01T00:00:00.000Z".into(),
+            address: address.into(),
+            challenge_id: format!("**D{day:02}C{number:02}"),
+            day,
+            challenge_number: number,
+            nonce: None,
+            crypto_receipt: None,
+        }
+    }
+
+    #[test]
+    fn rates_are_indexed_by_day_minus_one() {
+        let receipts = [
+            receipt("addr_a", 1, 1),
+            receipt("addr_b", 1, 1),
+            receipt("addr_a", 2, 3),
+            receipt("addr_a", 3, 1),
+        ];
+        // Day 3 has no published rate yet
+        let stats = collect(&receipts, &[2_000_000, 500_000], &[], &[]);
+
+        let days: Vec<_> = stats.days.iter().map(|d| (d.day, d.solutions, d.rate, d.star)).collect();
+        assert_eq!(
+            days,
+            [(1, 2, Some(2_000_000), 4_000_000), (2, 1, Some(500_000), 500_000), (3, 1, None, 0)]
+        );
+        assert_eq!(stats.totals.solutions, 4);
+        assert_eq!(stats.totals.star, 4_500_000);
+        assert_eq!(stats.totals.night, 4.5);
+    }
+
+    #[test]
+    fn day_zero_has_no_rate() {
+        let stats = collect(&[receipt("addr_a", 0, 1)], &[1_000_000], &[], &[]);
+        assert_eq!(stats.days[0].rate, None);
+        assert_eq!(stats.totals.star, 0);
+    }
+
+    #[test]
+    fn breaks_down_per_address_and_challenge() {
+        let receipts = [receipt("addr_a", 1, 1), receipt("addr_b", 1, 1), receipt("addr_b", 1, 2)];
+        let donations = [DonationRecord {
+            source: "addr_a".into(),
+            target: "addr_dest".into(),
+            timestamp: "2025-11-02T00:00:00Z".into(),
+        }];
+        let challenges = [Challenge {
+            challenge_id: "**D01C01".into(),
+            day: 1,
+            challenge_number: 1,
+            issued_at: String::new(),
+            latest_submission: String::new(),
+            difficulty: "000FFFFF".into(),
+            no_pre_mine: String::new(),
+            no_pre_mine_hour: String::new(),
+        }];
+        let stats = collect(&receipts, &[], &donations, &challenges);
+
+        let addresses: Vec<_> = stats
+            .addresses
+            .iter()
+            .map(|a| (a.address.as_str(), a.solutions, a.donated_to.as_deref()))
+            .collect();
+        assert_eq!(addresses, [("addr_a", 1, Some("addr_dest")), ("addr_b", 2, None)]);
+
+        let per_challenge: Vec<_> = stats
+            .challenges
+            .iter()
+            .map(|c| (c.challenge_id.as_str(), c.solutions, c.difficulty.as_deref()))
+            .collect();
+        assert_eq!(per_challenge, [("**D01C01", 2, Some("000FFFFF")), ("**D01C02", 1, None)]);
+    }
+
+    #[test]
+    fn csv_cells_are_quoted_when_needed() {
+        assert_eq!(csv_cell("addr1qx"), "addr1qx");
+        assert_eq!(csv_cell("a,b"), "\"a,b\"");
+        assert_eq!(csv_cell("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_cell("two\nlines"), "\"two\nlines\"");
+    }
+}
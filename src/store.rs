@@ -0,0 +1,678 @@
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::accounting::{ReceiptRecord, RegistrationRecord};
+use crate::api::types::{Challenge, CryptoReceipt, RegistrationReceiptInner};
+use crate::donations::DonationRecord;
+
+/// Schema migrations, applied in order. `PRAGMA user_version` holds the
+/// number already applied.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE receipts (
+        id               INTEGER PRIMARY KEY,
+        timestamp        TEXT NOT NULL,
+        address          TEXT NOT NULL,
+        challenge_id     TEXT NOT NULL,
+        day              INTEGER NOT NULL,
+        challenge_number INTEGER NOT NULL,
+        UNIQUE (address, challenge_id)
+    );
+    CREATE INDEX receipts_day ON receipts (day);
+    CREATE INDEX receipts_challenge ON receipts (challenge_id);
+
+    CREATE TABLE addresses (
+        address       TEXT PRIMARY KEY,
+        pubkey        TEXT NOT NULL,
+        registered_at TEXT NOT NULL
+    );
+
+    CREATE TABLE challenges (
+        challenge_id      TEXT PRIMARY KEY,
+        day               INTEGER NOT NULL,
+        challenge_number  INTEGER NOT NULL,
+        issued_at         TEXT NOT NULL,
+        latest_submission TEXT NOT NULL,
+        difficulty        TEXT NOT NULL,
+        no_pre_mine       TEXT NOT NULL,
+        no_pre_mine_hour  TEXT NOT NULL
+    );
+    CREATE INDEX challenges_day ON challenges (day);
+
+    CREATE TABLE donations (
+        source    TEXT PRIMARY KEY,
+        target    TEXT NOT NULL,
+        timestamp TEXT NOT NULL
+    );
+    CREATE INDEX donations_target ON donations (target);
+
+    CREATE TABLE star_rates (
+        day  INTEGER PRIMARY KEY,
+        rate INTEGER NOT NULL
+    );
+
+    CREATE TABLE submission_attempts (
+        id           INTEGER PRIMARY KEY,
+        address      TEXT NOT NULL,
+        challenge_id TEXT NOT NULL,
+        nonce        TEXT NOT NULL,
+        attempted_at TEXT NOT NULL,
+        outcome      TEXT NOT NULL,
+        detail       TEXT
+    );
+    CREATE INDEX submission_attempts_solution ON submission_attempts (address, challenge_id);
+
+    CREATE TABLE meta (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    // 2: full crypto receipts, submitted nonces and registration receipts
+    "ALTER TABLE receipts ADD COLUMN nonce TEXT;
+    ALTER TABLE receipts ADD COLUMN receipt_preimage TEXT;
+    ALTER TABLE receipts ADD COLUMN receipt_timestamp TEXT;
+    ALTER TABLE receipts ADD COLUMN receipt_signature TEXT;
+
+    ALTER TABLE addresses ADD COLUMN registration_preimage TEXT;
+    ALTER TABLE addresses ADD COLUMN registration_timestamp TEXT;
+    ALTER TABLE addresses ADD COLUMN registration_signature TEXT;",
+    // 3: date-filtered reports
+    "CREATE INDEX receipts_timestamp ON receipts (timestamp);",
+];
+
+/// `meta` key set once the legacy JSONL files have been imported.
+const JSONL_IMPORTED: &str = "jsonl_imported";
+
+/// Files written before the SQLite store existed (and by `export_jsonl`).
+pub const RECEIPTS_JSONL: &str = "00receipts.jsonl";
+pub const REGISTRATIONS_JSONL: &str = "00registrations.jsonl";
+pub const DONATIONS_JSONL: &str = "00donations.jsonl";
+pub const CHALLENGES_JSONL: &str = "00challenges.jsonl";
+pub const STAR_RATES_JSON: &str = "00star_rates.json";
+
+/// Embedded SQLite database with everything we account for:
+///   keystore/00accounting.sqlite
+///
+/// Opening it applies pending migrations and, the first time, imports the
+/// JSONL files that used to hold this data (they are left in place).
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+/// Rows written by `export_jsonl`.
+#[derive(Debug, Default)]
+pub struct ExportCounts {
+    pub receipts: usize,
+    pub registrations: usize,
+    pub donations: usize,
+    pub challenges: usize,
+    pub star_rates: usize,
+}
+
+impl Store {
+    pub const FILE: &'static str = "00accounting.sqlite";
+
+    /// Construct using env var KEYSTORE (defaults to "keystore")
+    pub fn new_from_env() -> Result<Self> {
+        let root = std::env::var("KEYSTORE").unwrap_or_else(|_| "keystore".to_string());
+        Self::new(root)
+    }
+
+    /// Open (or create) the database in an explicit keystore directory.
+    pub fn new<P: AsRef<Path>>(keystore_dir: P) -> Result<Self> {
+        let root = keystore_dir.as_ref();
+        fs::create_dir_all(root)?;
+
+        let mut conn = Connection::open(root.join(Self::FILE))?;
+        // Accounting, donations and the scheduler each hold a connection
+        conn.busy_timeout(std::time::Duration::from_secs(10))?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+
+        migrate(&mut conn)?;
+        import_jsonl(&mut conn, root)?;
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let mut conn = self.conn.lock().unwrap();
+        Ok(f(&mut conn)?)
+    }
+
+    //
+    // RECEIPTS
+    //
+
+    /// Store a receipt; returns false if one for the same address and
+    /// challenge is already stored.
+    pub fn insert_receipt(&self, rec: &ReceiptRecord) -> Result<bool> {
+        self.with(|c| insert_receipt(c, rec)).map(|n| n > 0)
+    }
+
+    /// All receipts in insertion order.
+    pub fn receipts(&self) -> Result<Vec<ReceiptRecord>> {
+        self.receipts_between(None, None)
+    }
+
+    /// Receipts with `since <= timestamp < until` (ISO strings, so a bare
+    /// date like "2025-11-04" bounds by UTC day), in insertion order.
+    pub fn receipts_between(
+        &self,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<ReceiptRecord>> {
+        self.with(|c| {
+            let mut stmt = c.prepare(
+                "SELECT timestamp, address, challenge_id, day, challenge_number, nonce,
+                        receipt_preimage, receipt_timestamp, receipt_signature
+                 FROM receipts
+                 WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp < ?2)
+                 ORDER BY id",
+            )?;
+            let rows = stmt.query_map(params![since, until], |r| {
+                Ok(ReceiptRecord {
+                    timestamp: r.get(0)?,
+                    address: r.get(1)?,
+                    challenge_id: r.get(2)?,
+                    day: r.get(3)?,
+                    challenge_number: r.get(4)?,
+                    nonce: r.get(5)?,
+                    crypto_receipt: crypto_receipt(r.get(6)?, r.get(7)?, r.get(8)?),
+                })
+            })?;
+            rows.collect()
+        })
+    }
+
+    pub fn receipt_count(&self) -> Result<u64> {
+        self.with(|c| c.query_row("SELECT COUNT(*) FROM receipts", [], |r| r.get(0)))
+    }
+
+    /// (day, number of receipts) for every day we have receipts for.
+    pub fn receipts_per_day(&self) -> Result<Vec<(u32, u64)>> {
+        self.with(|c| {
+            let mut stmt =
+                c.prepare("SELECT day, COUNT(*) FROM receipts GROUP BY day ORDER BY day")?;
+            let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+            rows.collect()
+        })
+    }
+
+    pub fn has_receipts(&self, address: &str) -> Result<bool> {
+        self.with(|c| {
+            c.query_row(
+                "SELECT EXISTS (SELECT 1 FROM receipts WHERE address = ?1)",
+                [address],
+                |r| r.get(0),
+            )
+        })
+    }
+
+    //
+    // STAR RATES
+    //
+
+    /// Replace the daily STAR rates (index 0 => day 1).
+    pub fn set_star_rates(&self, rates: &[u64]) -> Result<()> {
+        self.with(|c| {
+            let tx = c.transaction()?;
+            tx.execute("DELETE FROM star_rates", [])?;
+            insert_star_rates(&tx, rates)?;
+            tx.commit()
+        })
+    }
+
+    /// Daily STAR rates (index 0 => day 1).
+    pub fn star_rates(&self) -> Result<Vec<u64>> {
+        self.with(|c| {
+            let mut stmt = c.prepare("SELECT rate FROM star_rates ORDER BY day")?;
+            let rows = stmt.query_map([], |r| r.get(0))?;
+            rows.collect()
+        })
+    }
+
+    //
+    // DONATIONS
+    //
+
+    pub fn insert_donation(&self, rec: &DonationRecord) -> Result<()> {
+        self.with(|c| insert_donation(c, rec)).map(drop)
+    }
+
+    pub fn donations(&self) -> Result<Vec<DonationRecord>> {
+        self.with(|c| {
+            let mut stmt =
+                c.prepare("SELECT source, target, timestamp FROM donations ORDER BY rowid")?;
+            let rows = stmt.query_map([], |r| {
+                Ok(DonationRecord { source: r.get(0)?, target: r.get(1)?, timestamp: r.get(2)? })
+            })?;
+            rows.collect()
+        })
+    }
+
+    /// Whether `address` donated to anyone.
+    pub fn has_donated(&self, address: &str) -> Result<bool> {
+        self.with(|c| {
+            c.query_row(
+                "SELECT EXISTS (SELECT 1 FROM donations WHERE source = ?1)",
+                [address],
+                |r| r.get(0),
+            )
+        })
+    }
+
+    /// Whether anyone donated to `address`.
+    pub fn has_received(&self, address: &str) -> Result<bool> {
+        self.with(|c| {
+            c.query_row(
+                "SELECT EXISTS (SELECT 1 FROM donations WHERE target = ?1)",
+                [address],
+                |r| r.get(0),
+            )
+        })
+    }
+
+    //
+    // CHALLENGES
+    //
+
+    /// Store a challenge; returns false if it was already known.
+    pub fn insert_challenge(&self, ch: &Challenge) -> Result<bool> {
+        self.with(|c| insert_challenge(c, ch)).map(|n| n > 0)
+    }
+
+    pub fn challenges(&self) -> Result<Vec<Challenge>> {
+        self.with(|c| {
+            let mut stmt = c.prepare(
+                "SELECT challenge_id, day, challenge_number, issued_at, latest_submission,
+                        difficulty, no_pre_mine, no_pre_mine_hour
+                 FROM challenges ORDER BY rowid",
+            )?;
+            let rows = stmt.query_map([], |r| {
+                Ok(Challenge {
+                    challenge_id: r.get(0)?,
+                    day: r.get(1)?,
+                    challenge_number: r.get(2)?,
+                    issued_at: r.get(3)?,
+                    latest_submission: r.get(4)?,
+                    difficulty: r.get(5)?,
+                    no_pre_mine: r.get(6)?,
+                    no_pre_mine_hour: r.get(7)?,
+                })
+            })?;
+            rows.collect()
+        })
+    }
+
+    //
+    // ADDRESSES AND SUBMISSIONS
+    //
+
+    /// Remember that `address` was registered with `pubkey_hex` and keep the
+    /// latest registration receipt (the first registration time is kept).
+    pub fn record_registration(
+        &self,
+        address: &str,
+        pubkey_hex: &str,
+        receipt: &RegistrationReceiptInner,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.with(|c| {
+            c.execute(
+                "INSERT INTO addresses
+                    (address, pubkey, registered_at,
+                     registration_preimage, registration_timestamp, registration_signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (address) DO UPDATE SET
+                    pubkey = excluded.pubkey,
+                    registration_preimage = excluded.registration_preimage,
+                    registration_timestamp = excluded.registration_timestamp,
+                    registration_signature = excluded.registration_signature",
+                params![
+                    address,
+                    pubkey_hex,
+                    now,
+                    receipt.preimage,
+                    receipt.timestamp,
+                    receipt.signature
+                ],
+            )
+        })
+        .map(drop)
+    }
+
+    /// Registration of `address`, if we registered it.
+    pub fn registration(&self, address: &str) -> Result<Option<RegistrationRecord>> {
+        self.with(|c| {
+            c.query_row(
+                &format!("{REGISTRATION_COLUMNS} WHERE address = ?1"),
+                [address],
+                registration_row,
+            )
+            .optional()
+        })
+    }
+
+    pub fn registrations(&self) -> Result<Vec<RegistrationRecord>> {
+        self.with(|c| {
+            let mut stmt = c.prepare(&format!("{REGISTRATION_COLUMNS} ORDER BY rowid"))?;
+            let rows = stmt.query_map([], registration_row)?;
+            rows.collect()
+        })
+    }
+
+    /// Log one `/solution` call and how it ended.
+    pub fn record_attempt(
+        &self,
+        address: &str,
+        challenge_id: &str,
+        nonce: &str,
+        outcome: &str,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.with(|c| {
+            c.execute(
+                "INSERT INTO submission_attempts
+                    (address, challenge_id, nonce, attempted_at, outcome, detail)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![address, challenge_id, nonce, now, outcome, detail],
+            )
+        })
+        .map(drop)
+    }
+
+    //
+    // JSONL EXPORT
+    //
+
+    /// Write receipts, donations, challenges and STAR rates to `dir` in the
+    /// JSONL/JSON layout used before the SQLite store, plus the registrations.
+    pub fn export_jsonl(&self, dir: &Path) -> Result<ExportCounts> {
+        fs::create_dir_all(dir)?;
+        let receipts = self.receipts()?;
+        let registrations = self.registrations()?;
+        let donations = self.donations()?;
+        let challenges = self.challenges()?;
+        let star_rates = self.star_rates()?;
+
+        write_jsonl(&dir.join(RECEIPTS_JSONL), &receipts)?;
+        write_jsonl(&dir.join(REGISTRATIONS_JSONL), &registrations)?;
+        write_jsonl(&dir.join(DONATIONS_JSONL), &donations)?;
+        write_jsonl(&dir.join(CHALLENGES_JSONL), &challenges)?;
+        fs::write(dir.join(STAR_RATES_JSON), serde_json::to_string_pretty(&star_rates)?)?;
+
+        Ok(ExportCounts {
+            receipts: receipts.len(),
+            registrations: registrations.len(),
+            donations: donations.len(),
+            challenges: challenges.len(),
+            star_rates: star_rates.len(),
+        })
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: usize = tx.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "{} has schema version {} but this build only knows {}",
+            Store::FILE,
+            version,
+            MIGRATIONS.len()
+        );
+    }
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tracing::info!("Accounting database migrated to schema version {}", i + 1);
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// One-time import of the JSONL files from `root`.
+fn import_jsonl(conn: &mut Connection, root: &Path) -> Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let done: Option<String> = tx
+        .query_row("SELECT value FROM meta WHERE key = ?1", [JSONL_IMPORTED], |r| r.get(0))
+        .optional()?;
+    if done.is_some() {
+        return Ok(());
+    }
+
+    let receipts: Vec<ReceiptRecord> = read_jsonl(&root.join(RECEIPTS_JSONL), "receipt")?;
+    let donations: Vec<DonationRecord> = read_jsonl(&root.join(DONATIONS_JSONL), "donation")?;
+    let challenges: Vec<Challenge> = read_jsonl(&root.join(CHALLENGES_JSONL), "challenge")?;
+    let star_rates_path = root.join(STAR_RATES_JSON);
+    let star_rates: Vec<u64> = if star_rates_path.exists() {
+        serde_json::from_str(&fs::read_to_string(&star_rates_path)?)?
+    } else {
+        Vec::new()
+    };
+
+    let mut imported = ExportCounts::default();
+    for rec in &receipts {
+        imported.receipts += insert_receipt(&tx, rec)?;
+    }
+    for rec in &donations {
+        imported.donations += insert_donation(&tx, rec)?;
+    }
+    for ch in &challenges {
+        imported.challenges += insert_challenge(&tx, ch)?;
+    }
+    insert_star_rates(&tx, &star_rates)?;
+    imported.star_rates = star_rates.len();
+
+    tx.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+        params![JSONL_IMPORTED, chrono::Utc::now().to_rfc3339()],
+    )?;
+    tx.commit()?;
+
+    if imported.receipts + imported.donations + imported.challenges + imported.star_rates > 0 {
+        tracing::info!(
+            "Imported {} receipts, {} donations, {} challenges and {} STAR rates from JSONL into {}",
+            imported.receipts,
+            imported.donations,
+            imported.challenges,
+            imported.star_rates,
+            Store::FILE
+        );
+    }
+    Ok(())
+}
+
+fn insert_receipt(c: &Connection, rec: &ReceiptRecord) -> rusqlite::Result<usize> {
+    let receipt = rec.crypto_receipt.as_ref();
+    c.execute(
+        "INSERT OR IGNORE INTO receipts
+            (timestamp, address, challenge_id, day, challenge_number, nonce,
+             receipt_preimage, receipt_timestamp, receipt_signature)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            rec.timestamp,
+            rec.address,
+            rec.challenge_id,
+            rec.day,
+            rec.challenge_number,
+            rec.nonce,
+            receipt.map(|r| &r.preimage),
+            receipt.map(|r| &r.timestamp),
+            receipt.map(|r| &r.signature)
+        ],
+    )
+}
+
+/// A crypto receipt from its columns (all set, or none).
+fn crypto_receipt(
+    preimage: Option<String>,
+    timestamp: Option<String>,
+    signature: Option<String>,
+) -> Option<CryptoReceipt> {
+    Some(CryptoReceipt { preimage: preimage?, timestamp: timestamp?, signature: signature? })
+}
+
+const REGISTRATION_COLUMNS: &str = "SELECT address, pubkey, registered_at,
+    registration_preimage, registration_timestamp, registration_signature FROM addresses";
+
+fn registration_row(r: &rusqlite::Row) -> rusqlite::Result<RegistrationRecord> {
+    let (preimage, timestamp, signature): (Option<String>, Option<String>, Option<String>) =
+        (r.get(3)?, r.get(4)?, r.get(5)?);
+    Ok(RegistrationRecord {
+        address: r.get(0)?,
+        pubkey: r.get(1)?,
+        registered_at: r.get(2)?,
+        receipt: match (preimage, timestamp, signature) {
+            (Some(preimage), Some(timestamp), Some(signature)) => {
+                Some(RegistrationReceiptInner { preimage, timestamp, signature })
+            }
+            _ => None,
+        },
+    })
+}
+
+fn insert_donation(c: &Connection, rec: &DonationRecord) -> rusqlite::Result<usize> {
+    c.execute(
+        "INSERT OR IGNORE INTO donations (source, target, timestamp) VALUES (?1, ?2, ?3)",
+        params![rec.source, rec.target, rec.timestamp],
+    )
+}
+
+fn insert_challenge(c: &Connection, ch: &Challenge) -> rusqlite::Result<usize> {
+    c.execute(
+        "INSERT OR IGNORE INTO challenges
+            (challenge_id, day, challenge_number, issued_at, latest_submission,
+             difficulty, no_pre_mine, no_pre_mine_hour)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            ch.challenge_id,
+            ch.day,
+            ch.challenge_number,
+            ch.issued_at,
+            ch.latest_submission,
+            ch.difficulty,
+            ch.no_pre_mine,
+            ch.no_pre_mine_hour
+        ],
+    )
+}
+
+fn insert_star_rates(c: &Connection, rates: &[u64]) -> rusqlite::Result<()> {
+    let mut stmt = c.prepare("INSERT OR REPLACE INTO star_rates (day, rate) VALUES (?1, ?2)")?;
+    for (i, rate) in rates.iter().enumerate() {
+        stmt.execute(params![i + 1, rate])?;
+    }
+    Ok(())
+}
+
+/// Read a JSONL file (missing file => empty), skipping malformed lines.
+pub fn read_jsonl<T: DeserializeOwned>(path: &Path, what: &str) -> Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let f = OpenOptions::new().read(true).open(path)?;
+    let reader = BufReader::new(f);
+    let mut out = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<T>(&line) {
+            Ok(rec) => out.push(rec),
+            Err(e) => {
+                // keep going if there's a corrupt line
+                tracing::warn!("Ignoring malformed {} line: {e}", what);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Write `items` as one JSON object per line (replacing `path`).
+pub fn write_jsonl<T: Serialize>(path: &Path, items: &[T]) -> Result<()> {
+    let mut f = fs::File::create(path)?;
+    for item in items {
+        writeln!(f, "{}", serde_json::to_string(item)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("scavenger-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn donation(source: &str) -> DonationRecord {
+        DonationRecord {
+            source: source.into(),
+            target: "addr_target".into(),
+            timestamp: "2025-11-04T21:26:06Z".into(),
+        }
+    }
+
+    #[test]
+    fn migrates_a_version_1_database() {
+        let dir = temp_dir("store-migrate");
+        {
+            let conn = Connection::open(dir.join(Store::FILE)).unwrap();
+            conn.execute_batch(MIGRATIONS[0]).unwrap();
+            conn.pragma_update(None, "user_version", 1).unwrap();
+            conn.execute(
+                "INSERT INTO receipts (timestamp, address, challenge_id, day, challenge_number)
+                 VALUES ('2025-11-04T21:26:06Z', 'addr1', '**D06C22', 6, 22)",
+                [],
+            )
+            .unwrap();
+        }
+
+        let store = Store::new(&dir).unwrap();
+        let version: usize = store.with(|c| c.query_row("PRAGMA user_version", [], |r| r.get(0))).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        // Old rows survive, with the columns added later left empty
+        let receipts = store.receipts().unwrap();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].challenge_id, "**D06C22");
+        assert!(receipts[0].nonce.is_none() && receipts[0].crypto_receipt.is_none());
+        assert_eq!(store.receipts_between(Some("2025-11-04"), Some("2025-11-05")).unwrap().len(), 1);
+
+        // Reopening is a no-op
+        drop(store);
+        assert_eq!(Store::new(&dir).unwrap().receipt_count().unwrap(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jsonl_is_imported_once() {
+        let dir = temp_dir("store-import");
+        write_jsonl(&dir.join(DONATIONS_JSONL), &[donation("addr1")]).unwrap();
+        fs::write(dir.join(STAR_RATES_JSON), "[10, 20]").unwrap();
+
+        let store = Store::new(&dir).unwrap();
+        assert_eq!(store.donations().unwrap().len(), 1);
+        assert_eq!(store.star_rates().unwrap(), [10, 20]);
+        drop(store);
+
+        // Files changed after the import are not read again
+        write_jsonl(&dir.join(DONATIONS_JSONL), &[donation("addr1"), donation("addr2")]).unwrap();
+        let store = Store::new(&dir).unwrap();
+        assert_eq!(store.donations().unwrap().len(), 1);
+        assert!(!store.has_donated("addr2").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,10 @@
 use ciborium::{ser, value::Value};
-use ed25519_dalek::{SigningKey, Signer, SecretKey};
-use anyhow::Result;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::address::PrivateKey;
+use crate::util::bech::blake2b224;
 
 fn cbor_to_vec(v: &Value) -> Vec<u8> {
     let mut out = Vec::new();
@@ -8,13 +12,34 @@ fn cbor_to_vec(v: &Value) -> Vec<u8> {
     out
 }
 
+/// Sig_structure = ["Signature1", protected_bstr, h"", payload_bstr]
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Vec<u8> {
+    cbor_to_vec(&Value::Array(vec![
+        Value::Text("Signature1".into()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(payload.to_vec()),
+    ]))
+}
+
+/// Payload as embedded in COSE_Sign1: the message itself, or its
+/// blake2b-224 hash in hashed mode (CIP-8).
+pub fn signed_payload(message: &[u8], hashed: bool) -> Vec<u8> {
+    if hashed {
+        blake2b224(message).to_vec()
+    } else {
+        message.to_vec()
+    }
+}
+
 /// Build COSE_Sign1 for /register (CIP-8/30 style, as in docs):
 /// - protected: {1: -8}  (alg = EdDSA)
-/// - unprotected: { "address": bstr(raw_address), "hashed": false }
-/// - payload: exact T&C text (UTF-8, trimmed by caller if needed)
+/// - unprotected: { "address": bstr(raw_address), "hashed": bool }
+/// - payload: exact T&C text (UTF-8, trimmed by caller if needed), or its
+///   blake2b-224 hash when `hashed`
 /// - signature: Ed25519 over Sig_structure = ["Signature1", protected_bstr, h"", payload_bstr]
 pub fn cose_sign1_ed25519_with_headers(
-    privkey: &[u8; 32],
+    privkey: &PrivateKey,
     payload_utf8: &str,
     address_raw: &[u8],
     hashed: bool,
@@ -25,27 +50,16 @@ pub fn cose_sign1_ed25519_with_headers(
     ]);
     let protected_bstr = cbor_to_vec(&protected_map);
 
-    // unprotected header map { "address": bstr(...), "hashed": false }
+    // unprotected header map { "address": bstr(...), "hashed": bool }
     let unprotected = Value::Map(vec![
         (Value::Text("address".into()), Value::Bytes(address_raw.to_vec())),
         (Value::Text("hashed".into()),  Value::Bool(hashed)),
     ]);
 
     // payload bytes (bstr)
-    let payload_bytes = payload_utf8.as_bytes().to_vec();
+    let payload_bytes = signed_payload(payload_utf8.as_bytes(), hashed);
 
-    // Sig_structure = ["Signature1", protected_bstr, external_aad(b""), payload_bstr]
-    let sig_structure = Value::Array(vec![
-        Value::Text("Signature1".into()),
-        Value::Bytes(protected_bstr.clone()),
-        Value::Bytes(Vec::new()),
-        Value::Bytes(payload_bytes.clone()),
-    ]);
-    let to_sign = cbor_to_vec(&sig_structure);
-
-    // Ed25519 sign
-    let sk = SigningKey::from_bytes(privkey);
-    let sig = sk.sign(&to_sign).to_bytes().to_vec();
+    let sig = privkey.sign(&sig_structure(&protected_bstr, &payload_bytes)).to_vec();
 
     // COSE_Sign1 array: [protected_bstr, unprotected_map, payload_bstr, signature_bstr]
     let cose_sign1 = Value::Array(vec![
@@ -58,36 +72,28 @@ pub fn cose_sign1_ed25519_with_headers(
     cbor_to_vec(&cose_sign1)
 }
 
+/// Build COSE_Sign1 for /donate_to: empty headers and the raw message, or
+/// {"hashed": true} in the unprotected header and its blake2b-224 hash.
 pub fn cose_sign1_donate(
-    privkey: &[u8; 32],
+    privkey: &PrivateKey,
     payload_utf8: &str,
+    hashed: bool,
 ) -> Vec<u8> {
-    use ciborium::{ser, value::Value};
-    use ed25519_dalek::{SigningKey, Signer};
-
     // protected header: empty map
     let protected = Value::Map(vec![]);
     let protected_bstr = cbor_to_vec(&protected);
 
-    // unprotected header: empty map
-    let unprotected = Value::Map(vec![]);
+    // unprotected header: empty map unless hashed
+    let unprotected = if hashed {
+        Value::Map(vec![(Value::Text("hashed".into()), Value::Bool(true))])
+    } else {
+        Value::Map(vec![])
+    };
 
-    // payload raw bytes
-    let payload_bytes = payload_utf8.as_bytes().to_vec();
+    // payload bytes
+    let payload_bytes = signed_payload(payload_utf8.as_bytes(), hashed);
 
-    // Sig_structure = ["Signature1", protected_bstr, h"", payload]
-    let sig_structure = Value::Array(vec![
-        Value::Text("Signature1".into()),
-        Value::Bytes(protected_bstr.clone()),
-        Value::Bytes(vec![]),
-        Value::Bytes(payload_bytes.clone()),
-    ]);
-
-    let to_sign = cbor_to_vec(&sig_structure);
-
-    // Ed25519 sign
-    let sk = SigningKey::from_bytes(privkey);
-    let sig = sk.sign(&to_sign).to_bytes().to_vec();
+    let sig = privkey.sign(&sig_structure(&protected_bstr, &payload_bytes)).to_vec();
 
     // COSE_Sign1 = [ protected_bstr, unprotected_map, payload_bstr, signature_bstr ]
     let cose_sign1 = Value::Array(vec![
@@ -98,4 +104,331 @@ pub fn cose_sign1_donate(
     ]);
 
     cbor_to_vec(&cose_sign1)
-}
\ No newline at end of file
+}
+
+/// CIP-30 `signData` result: COSE_Sign1 and COSE_Key, both CBOR hex.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataSignature {
+    pub signature: String,
+    pub key: String,
+}
+
+/// CIP-30 `signData` (CIP-8 layout):
+/// - protected: {1: -8, "address": bstr(raw_address)}
+/// - unprotected: {"hashed": bool}
+/// - key: COSE_Key for the signing key
+pub fn sign_data(privkey: &PrivateKey, message: &[u8], address_raw: &[u8], hashed: bool) -> DataSignature {
+    let payload = signed_payload(message, hashed);
+    let protected = Value::Map(vec![
+        (Value::Integer(LABEL_ALG.into()), Value::Integer(ALG_EDDSA.into())),
+        (Value::Text("address".into()), Value::Bytes(address_raw.to_vec())),
+    ]);
+    let protected_bstr = cbor_to_vec(&protected);
+    let unprotected = Value::Map(vec![(Value::Text("hashed".into()), Value::Bool(hashed))]);
+
+    let sig = privkey.sign(&sig_structure(&protected_bstr, &payload)).to_vec();
+
+    let cose_sign1 = Value::Array(vec![
+        Value::Bytes(protected_bstr),
+        unprotected,
+        Value::Bytes(payload),
+        Value::Bytes(sig),
+    ]);
+
+    DataSignature {
+        signature: hex::encode(cbor_to_vec(&cose_sign1)),
+        key: hex::encode(cose_key_ed25519(&privkey.public_key())),
+    }
+}
+
+/// COSE_Key labels and values (RFC 9053) for an Ed25519 public key.
+const KEY_KTY: i64 = 1;
+const KEY_ALG: i64 = 3;
+const KEY_CRV: i64 = -1;
+const KEY_X: i64 = -2;
+const KTY_OKP: i64 = 1;
+const CRV_ED25519: i64 = 6;
+
+/// COSE_Key {1: 1 (OKP), 3: -8 (EdDSA), -1: 6 (Ed25519), -2: x}
+pub fn cose_key_ed25519(pubkey: &[u8; 32]) -> Vec<u8> {
+    cbor_to_vec(&Value::Map(vec![
+        (Value::Integer(KEY_KTY.into()), Value::Integer(KTY_OKP.into())),
+        (Value::Integer(KEY_ALG.into()), Value::Integer(ALG_EDDSA.into())),
+        (Value::Integer(KEY_CRV.into()), Value::Integer(CRV_ED25519.into())),
+        (Value::Integer(KEY_X.into()), Value::Bytes(pubkey.to_vec())),
+    ]))
+}
+
+/// Ed25519 public key from a COSE_Key (kty OKP, crv Ed25519).
+pub fn decode_cose_key(bytes: &[u8]) -> Result<[u8; 32]> {
+    let key: Value = ciborium::de::from_reader(bytes).map_err(|e| anyhow!("COSE_Key: {e}"))?;
+    let int = |label: i64| match map_get(&key, &Value::Integer(label.into())) {
+        Some(Value::Integer(i)) => i64::try_from(*i).ok(),
+        _ => None,
+    };
+    if !matches!(key, Value::Map(_)) {
+        bail!("COSE_Key must be a CBOR map");
+    }
+    if int(KEY_KTY) != Some(KTY_OKP) || int(KEY_CRV) != Some(CRV_ED25519) {
+        bail!("COSE_Key is not an Ed25519 (OKP) key");
+    }
+    if let Some(alg) = int(KEY_ALG) {
+        if alg != ALG_EDDSA {
+            bail!("COSE_Key algorithm {alg} is not EdDSA");
+        }
+    }
+    match map_get(&key, &Value::Integer(KEY_X.into())) {
+        Some(Value::Bytes(x)) => x
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("COSE_Key x must be 32 bytes, got {}", x.len())),
+        _ => bail!("COSE_Key has no x coordinate"),
+    }
+}
+
+/// COSE header labels used here (RFC 9052).
+pub const LABEL_ALG: i64 = 1;
+pub const LABEL_KID: i64 = 4;
+/// `alg` value of EdDSA.
+pub const ALG_EDDSA: i64 = -8;
+
+/// A decoded COSE_Sign1 structure.
+pub struct DecodedSign1 {
+    /// Protected header exactly as serialized (needed for Sig_structure).
+    pub protected_bstr: Vec<u8>,
+    /// `protected_bstr` decoded (an empty bstr is an empty map).
+    pub protected: Value,
+    pub unprotected: Value,
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl DecodedSign1 {
+    /// The bytes the signature covers.
+    pub fn sig_structure(&self) -> Vec<u8> {
+        sig_structure(&self.protected_bstr, &self.payload)
+    }
+
+    /// Verify the Ed25519 signature against `pubkey`.
+    pub fn verify(&self, pubkey: &[u8; 32]) -> Result<()> {
+        match self.alg() {
+            None | Some(ALG_EDDSA) => {}
+            Some(alg) => bail!("unsupported COSE algorithm {alg} (only EdDSA)"),
+        }
+        let vk = VerifyingKey::from_bytes(pubkey)?;
+        let sig_bytes: [u8; 64] = self
+            .signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("signature must be 64 bytes, got {}", self.signature.len()))?;
+        vk.verify(&self.sig_structure(), &Signature::from_bytes(&sig_bytes))?;
+        Ok(())
+    }
+
+    /// Header value for `label`, protected header first.
+    pub fn header(&self, label: &Value) -> Option<&Value> {
+        [&self.protected, &self.unprotected]
+            .into_iter()
+            .find_map(|h| map_get(h, label))
+    }
+
+    /// `alg` (label 1), if present.
+    pub fn alg(&self) -> Option<i64> {
+        match self.header(&Value::Integer(LABEL_ALG.into()))? {
+            Value::Integer(i) => i64::try_from(*i).ok(),
+            _ => None,
+        }
+    }
+
+    /// Raw address from the `address` header, if present.
+    pub fn address(&self) -> Option<&[u8]> {
+        match self.header(&Value::Text("address".into()))? {
+            Value::Bytes(b) => Some(b.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Whether the payload is the blake2b-224 hash of the message.
+    pub fn hashed(&self) -> bool {
+        matches!(self.header(&Value::Text("hashed".into())), Some(Value::Bool(true)))
+    }
+
+    /// Whether the payload is `message` (or its hash, in hashed mode).
+    pub fn payload_matches(&self, message: &[u8]) -> bool {
+        self.payload == signed_payload(message, self.hashed())
+    }
+
+    /// Ed25519 public key carried in the `kid` header, if it holds one.
+    pub fn embedded_key(&self) -> Option<[u8; 32]> {
+        match self.header(&Value::Integer(LABEL_KID.into()))? {
+            Value::Bytes(b) => b.as_slice().try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// Byte-string value of a text label in the unprotected header.
+    pub fn unprotected_bytes(&self, label: &str) -> Option<&[u8]> {
+        match map_get(&self.unprotected, &Value::Text(label.into()))? {
+            Value::Bytes(b) => Some(b.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+fn map_get<'a>(map: &'a Value, label: &Value) -> Option<&'a Value> {
+    match map {
+        Value::Map(entries) => entries.iter().find(|(k, _)| k == label).map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+/// Parse COSE_Sign1 = [protected_bstr, unprotected_map, payload_bstr, signature_bstr]
+/// (optionally wrapped in tag 18).
+pub fn decode_cose_sign1(bytes: &[u8]) -> Result<DecodedSign1> {
+    let v: Value = ciborium::de::from_reader(bytes)?;
+    let v = match v {
+        Value::Tag(18, inner) => *inner,
+        v => v,
+    };
+    let Value::Array(items) = v else {
+        bail!("COSE_Sign1 must be a CBOR array");
+    };
+    let [protected, unprotected, payload, signature]: [Value; 4] = items
+        .try_into()
+        .map_err(|items: Vec<Value>| anyhow!("COSE_Sign1 must have 4 elements, got {}", items.len()))?;
+
+    let bytes_of = |v: Value, what: &str| match v {
+        Value::Bytes(b) => Ok(b),
+        _ => Err(anyhow!("COSE_Sign1 {} must be a byte string", what)),
+    };
+
+    let protected_bstr = bytes_of(protected, "protected header")?;
+    let protected = if protected_bstr.is_empty() {
+        Value::Map(Vec::new())
+    } else {
+        ciborium::de::from_reader(protected_bstr.as_slice())
+            .map_err(|e| anyhow!("COSE_Sign1 protected header: {e}"))?
+    };
+    if !matches!(protected, Value::Map(_)) || !matches!(unprotected, Value::Map(_)) {
+        bail!("COSE_Sign1 headers must be maps");
+    }
+
+    Ok(DecodedSign1 {
+        protected_bstr,
+        protected,
+        unprotected,
+        payload: bytes_of(payload, "payload")?,
+        signature: bytes_of(signature, "signature")?,
+    })
+}
+
+/// CBOR diagnostic notation of `v` (RFC 8949 §8), for display.
+pub fn diagnostic(v: &Value) -> String {
+    match v {
+        Value::Integer(i) => i128::from(*i).to_string(),
+        Value::Bytes(b) => format!("h'{}'", hex::encode(b)),
+        Value::Float(f) => f.to_string(),
+        Value::Text(t) => format!("{t:?}"),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => "null".into(),
+        Value::Tag(tag, inner) => format!("{tag}({})", diagnostic(inner)),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(diagnostic).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Map(entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", diagnostic(k), diagnostic(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        _ => "undefined".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8032 test 1 seed; vectors cross-checked with an independent
+    // Ed25519/CBOR implementation.
+    const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const MESSAGE: &str = "I agree";
+    const MESSAGE_HASH: &str = "5d0774a264fd0a6b43874ccc7db704db1cf4d5ccc43c4b6583ef726a";
+
+    const REGISTER_RAW: &str = "8443a10127a26761646472657373581d6000000000000000000000000000000000000000000000000000000000666861736865\
+        64f4474920616772656558403cb3c9158e6d91df964696324c7fa0af8eb388321c8570f683b9045b4caf949303aca030c8b6856cafaeeecba8966128c59c9d5c98\
+        77f9874e3b1dd94c514806";
+    const REGISTER_HASHED: &str = "8443a10127a26761646472657373581d6000000000000000000000000000000000000000000000000000000000666861736865\
+        64f5581c5d0774a264fd0a6b43874ccc7db704db1cf4d5ccc43c4b6583ef726a584073d9e86b890eda9a46fec217aed185d51100441c0583530a0fde27e1a50714cf\
+        20a9040d3db406e2d18f7370ec4b2a888864725a682b59d4b11cc7cc342fd708";
+    const DONATE_RAW: &str = "8441a0a04749206167726565584014035027bdb2cc241b82afdda235dd27a26814e64aae64f826dc95b2abbe219fd2b2561659d2\
+        555e5528c1d3f281e134ba8b7f48e95935c3f6db03da188f1e0b";
+    const DONATE_HASHED: &str = "8441a0a166686173686564f5581c5d0774a264fd0a6b43874ccc7db704db1cf4d5ccc43c4b6583ef726a58403547120450ebab\
+        3a488844c2ceb407bc6715debcc863c08077ecb5bb88f86613770da81166cfe7cba346469d0d057d90238298d073fe192de2df934efc4d9507";
+    const SIGN_DATA_RAW: &str = "84582aa201276761646472657373581d6000000000000000000000000000000000000000000000000000000000a1666861\
+        73686564f447492061677265655840beca6265ba725cf69c5a934799da483beff5540a04124a8d23cd475b406be03381eb25be468bdd25f7f4044b2bfe62fd\
+        f803bb01190d37ac8b0cb49241bfcd02";
+    const SIGN_DATA_HASHED: &str = "84582aa201276761646472657373581d6000000000000000000000000000000000000000000000000000000000a1666861\
+        73686564f5581c5d0774a264fd0a6b43874ccc7db704db1cf4d5ccc43c4b6583ef726a584017462352645999090600c626cecc3a7bccba8604352b934de0b8\
+        0b74602b8b807a395327d96ecb63a8d337170bfb1bd3180f742cce807b07419226606681c20c";
+    const COSE_KEY: &str = "a4010103272006215820d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    fn key() -> PrivateKey {
+        PrivateKey::from_bytes(&hex::decode(SEED).unwrap()).unwrap()
+    }
+
+    fn address() -> Vec<u8> {
+        let mut raw = vec![0x60];
+        raw.extend_from_slice(&[0u8; 28]);
+        raw
+    }
+
+    fn check(cose: &[u8], expected: &str, hashed: bool) {
+        assert_eq!(hex::encode(cose), expected);
+        let sign1 = decode_cose_sign1(cose).unwrap();
+        assert_eq!(sign1.hashed(), hashed);
+        assert!(sign1.payload_matches(MESSAGE.as_bytes()));
+        assert!(!sign1.payload_matches(b"something else"));
+        sign1.verify(&key().public_key()).unwrap();
+    }
+
+    #[test]
+    fn hashed_payload_is_blake2b224() {
+        assert_eq!(signed_payload(MESSAGE.as_bytes(), false), MESSAGE.as_bytes());
+        assert_eq!(hex::encode(signed_payload(MESSAGE.as_bytes(), true)), MESSAGE_HASH);
+    }
+
+    #[test]
+    fn register_vectors() {
+        let raw = cose_sign1_ed25519_with_headers(&key(), MESSAGE, &address(), false);
+        check(&raw, REGISTER_RAW, false);
+        let hashed = cose_sign1_ed25519_with_headers(&key(), MESSAGE, &address(), true);
+        check(&hashed, REGISTER_HASHED, true);
+    }
+
+    #[test]
+    fn donate_vectors() {
+        check(&cose_sign1_donate(&key(), MESSAGE, false), DONATE_RAW, false);
+        check(&cose_sign1_donate(&key(), MESSAGE, true), DONATE_HASHED, true);
+    }
+
+    #[test]
+    fn sign_data_vectors() {
+        for (hashed, expected) in [(false, SIGN_DATA_RAW), (true, SIGN_DATA_HASHED)] {
+            let ds = sign_data(&key(), MESSAGE.as_bytes(), &address(), hashed);
+            check(&hex::decode(&ds.signature).unwrap(), expected, hashed);
+            assert_eq!(decode_cose_sign1(&hex::decode(&ds.signature).unwrap()).unwrap().address(), Some(&address()[..]));
+            assert_eq!(ds.key, COSE_KEY);
+        }
+    }
+
+    #[test]
+    fn cose_key_round_trip() {
+        let pk = key().public_key();
+        let cose_key = cose_key_ed25519(&pk);
+        assert_eq!(hex::encode(&cose_key), COSE_KEY);
+        assert_eq!(decode_cose_key(&cose_key).unwrap(), pk);
+    }
+}